@@ -0,0 +1,994 @@
+// Off-chain helpers for building escrow instructions and reading escrow accounts
+use bytemuck::Zeroable;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+use spl_associated_token_account_client::address::get_associated_token_address_with_program_id;
+
+use crate::{
+    context::{
+        Make, MakeBundle, MakeCounterOffer, MakeService, MakeVesting, Migrate, OpenRegistryPage,
+        ResolveService, Take, TakePartial,
+    },
+    instructions::EscrowInstructions,
+    states::{
+        is_native, Bundle, CounterOffer, Escrow, OraclePricing, RegistryPage, Service, Vesting,
+        ESCROW_DISCRIMINATOR, MAX_BUNDLE_LEGS,
+    },
+};
+
+pub fn find_escrow_address(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), seed.to_le_bytes().as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_vault_address(escrow: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID)
+}
+
+pub fn find_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &crate::ID)
+}
+
+pub fn find_registry_address(mint_a: &Pubkey, mint_b: &Pubkey, page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"registry",
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            page.to_le_bytes().as_ref(),
+        ],
+        &crate::ID,
+    )
+}
+
+// Read an escrow account's data as stored on chain. Legacy accounts must be
+// migrated first.
+pub fn unpack_escrow(data: &[u8]) -> Result<Escrow, ProgramError> {
+    let escrow = bytemuck::try_pod_read_unaligned::<Escrow>(data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if escrow.discriminator != ESCROW_DISCRIMINATOR {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(escrow)
+}
+
+// Upgrade an escrow of an earlier version in place, with `payer` covering the extra rent
+pub fn migrate(payer: Pubkey, escrow: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(find_vault_address(&escrow).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: EscrowInstructions::Migrate(Migrate::zeroed()).pack(),
+    }
+}
+
+// Upgrade `maker`'s legacy-layout escrow in place, restating the terms it was made with
+pub fn migrate_legacy(maker: Pubkey, seed: u64, mint_b: Pubkey, receive: u64) -> Instruction {
+    let mut instruction = migrate(maker, find_escrow_address(&maker, seed).0);
+    instruction.data = EscrowInstructions::Migrate(Migrate {
+        seed,
+        receive,
+        mint_b,
+    })
+    .pack();
+    instruction
+}
+
+// Open escrows listed on a registry page
+pub fn unpack_registry_page(data: &[u8]) -> Result<Vec<Pubkey>, ProgramError> {
+    let page = bytemuck::try_pod_read_unaligned::<RegistryPage>(data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let count = (page.count as usize).min(page.escrows.len());
+    Ok(page.escrows[..count].to_vec())
+}
+
+// Registry slot for an escrow's instructions; the program id when it isn't listed
+fn registry_account(registry: Pubkey) -> AccountMeta {
+    if registry == Pubkey::default() {
+        AccountMeta::new_readonly(crate::ID, false)
+    } else {
+        AccountMeta::new(registry, false)
+    }
+}
+
+pub fn open_registry_page(payer: Pubkey, mint_a: Pubkey, mint_b: Pubkey, page: u32) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new(find_registry_address(&mint_a, &mint_b, page).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: EscrowInstructions::OpenRegistryPage(OpenRegistryPage { page }).pack(),
+    }
+}
+
+// Token account the program moves `mint` through for `wallet`; native SOL legs use the wallet itself
+fn token_account(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    if is_native(mint) {
+        *wallet
+    } else {
+        get_associated_token_address_with_program_id(wallet, mint, token_program)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MakeBuilder {
+    maker: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    token_program_a: Pubkey,
+    token_program_b: Pubkey,
+    seed: u64,
+    amount: u64,
+    receive: u64,
+    expiry: i64,
+    taker: Pubkey,
+    registry: Pubkey,
+    pricing: OraclePricing,
+    remaining_accounts: Vec<AccountMeta>,
+}
+
+impl MakeBuilder {
+    // Offer `amount` of `mint_a` for `receive` of `mint_b`, both on SPL Token, open to anyone and never expiring
+    pub fn new(
+        maker: Pubkey,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        seed: u64,
+        amount: u64,
+        receive: u64,
+    ) -> Self {
+        Self {
+            maker,
+            mint_a,
+            mint_b,
+            token_program_a: spl_token::ID,
+            token_program_b: spl_token::ID,
+            seed,
+            amount,
+            receive,
+            expiry: 0,
+            taker: Pubkey::default(),
+            registry: Pubkey::default(),
+            pricing: OraclePricing::default(),
+            remaining_accounts: Vec::new(),
+        }
+    }
+
+    pub fn token_programs(mut self, token_program_a: Pubkey, token_program_b: Pubkey) -> Self {
+        self.token_program_a = token_program_a;
+        self.token_program_b = token_program_b;
+        self
+    }
+
+    pub fn expiry(mut self, expiry: i64) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    pub fn taker(mut self, taker: Pubkey) -> Self {
+        self.taker = taker;
+        self
+    }
+
+    // Quote the ask from a price feed at each Take instead of `receive`
+    pub fn pricing(mut self, pricing: OraclePricing) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    // List the offer on this registry page for its mint pair, which must have room
+    pub fn registry_page(mut self, page: u32) -> Self {
+        self.registry = find_registry_address(&self.mint_a, &self.mint_b, page).0;
+        self
+    }
+
+    // Extra accounts required by a transfer hook on token A
+    pub fn remaining_accounts(mut self, remaining_accounts: Vec<AccountMeta>) -> Self {
+        self.remaining_accounts = remaining_accounts;
+        self
+    }
+
+    pub fn escrow(&self) -> Pubkey {
+        find_escrow_address(&self.maker, self.seed).0
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let escrow = self.escrow();
+        let args = Make {
+            seed: self.seed,
+            amount: self.amount,
+            receive: self.receive,
+            expiry: self.expiry,
+            taker: self.taker,
+            pricing: self.pricing,
+        };
+        let mut accounts = vec![
+            AccountMeta::new(self.maker, true),
+            AccountMeta::new_readonly(self.mint_a, false),
+            AccountMeta::new_readonly(self.mint_b, false),
+            AccountMeta::new(
+                token_account(&self.maker, &self.mint_a, &self.token_program_a),
+                false,
+            ),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(find_vault_address(&escrow).0, false),
+            AccountMeta::new_readonly(self.token_program_a, false),
+            AccountMeta::new_readonly(self.token_program_b, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            registry_account(self.registry),
+        ];
+        accounts.extend_from_slice(&self.remaining_accounts);
+        Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: EscrowInstructions::Make(args).pack(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TakeBuilder {
+    taker: Pubkey,
+    escrow: Escrow,
+    amount: Option<u64>,
+    token_program_a: Pubkey,
+    token_program_b: Pubkey,
+    treasury_ta: Option<Pubkey>,
+    relayer: Option<Pubkey>,
+    limits: Take,
+    remaining_accounts: Vec<AccountMeta>,
+}
+
+impl TakeBuilder {
+    // Fill the whole of `escrow`, as read with `unpack_escrow`
+    pub fn new(taker: Pubkey, escrow: Escrow) -> Self {
+        Self {
+            taker,
+            escrow,
+            amount: None,
+            token_program_a: spl_token::ID,
+            token_program_b: spl_token::ID,
+            treasury_ta: None,
+            relayer: None,
+            limits: Take::ANY,
+            remaining_accounts: Vec::new(),
+        }
+    }
+
+    // Fill for the taker as `relayer`, spending the approval the taker gave
+    // `relayer` on their token B account. Builds a TakeDelegated.
+    pub fn relayer(mut self, relayer: Pubkey) -> Self {
+        self.relayer = Some(relayer);
+        self
+    }
+
+    // Fail unless at least `min_amount` of token A arrives for at most
+    // `max_payment` of token B
+    pub fn limits(mut self, min_amount: u64, max_payment: u64) -> Self {
+        self.limits = Take {
+            min_amount,
+            max_payment,
+        };
+        self
+    }
+
+    // Only take `amount` of token A, building a TakePartial instead
+    pub fn amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn token_programs(mut self, token_program_a: Pubkey, token_program_b: Pubkey) -> Self {
+        self.token_program_a = token_program_a;
+        self.token_program_b = token_program_b;
+        self
+    }
+
+    // Account receiving the protocol fee: the treasury's token account for the
+    // fee leg's mint, or the treasury wallet itself when that leg is native SOL
+    pub fn treasury_ta(mut self, treasury_ta: Pubkey) -> Self {
+        self.treasury_ta = Some(treasury_ta);
+        self
+    }
+
+    // Extra accounts required by transfer hooks on either leg
+    pub fn remaining_accounts(mut self, remaining_accounts: Vec<AccountMeta>) -> Self {
+        self.remaining_accounts = remaining_accounts;
+        self
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let Escrow {
+            seed,
+            maker,
+            mint_a,
+            mint_b,
+            registry,
+            pricing,
+            ..
+        } = self.escrow;
+        let escrow = find_escrow_address(&maker, seed).0;
+        let config = find_config_address().0;
+        let mut accounts = vec![
+            AccountMeta::new(self.taker, self.relayer.is_none()),
+            AccountMeta::new(maker, false),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new_readonly(mint_b, false),
+            AccountMeta::new(
+                token_account(&self.taker, &mint_a, &self.token_program_a),
+                false,
+            ),
+            AccountMeta::new(
+                token_account(&self.taker, &mint_b, &self.token_program_b),
+                false,
+            ),
+            AccountMeta::new(token_account(&maker, &mint_b, &self.token_program_b), false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(find_vault_address(&escrow).0, false),
+            AccountMeta::new_readonly(self.token_program_a, false),
+            AccountMeta::new_readonly(self.token_program_b, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(spl_associated_token_account_client::program::ID, false),
+            AccountMeta::new_readonly(config, false),
+            // Unused until a fee is configured, so the config stands in for it
+            match self.treasury_ta {
+                Some(treasury_ta) => AccountMeta::new(treasury_ta, false),
+                None => AccountMeta::new_readonly(config, false),
+            },
+            registry_account(registry),
+            // Unused by fixed-price offers, so the program id stands in
+            AccountMeta::new_readonly(
+                if pricing.is_fixed() {
+                    crate::ID
+                } else {
+                    pricing.oracle
+                },
+                false,
+            ),
+        ];
+        accounts.extend_from_slice(&self.remaining_accounts);
+        let Take {
+            min_amount,
+            max_payment,
+        } = self.limits;
+        let partial = |amount| TakePartial {
+            amount,
+            min_amount,
+            max_payment,
+        };
+        let data = match (self.relayer, self.amount) {
+            (Some(relayer), amount) => {
+                accounts.insert(0, AccountMeta::new(relayer, true));
+                let amount = amount.unwrap_or(self.escrow.remaining);
+                EscrowInstructions::TakeDelegated(partial(amount)).pack()
+            }
+            (None, Some(amount)) => EscrowInstructions::TakePartial(partial(amount)).pack(),
+            (None, None) => EscrowInstructions::Take(self.limits).pack(),
+        };
+        Instruction {
+            program_id: crate::ID,
+            accounts,
+            data,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RefundBuilder {
+    escrow: Escrow,
+    token_program: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+}
+
+impl RefundBuilder {
+    // Return `escrow`'s deposit and rent to its maker, who must sign unless the offer has expired
+    pub fn new(escrow: Escrow) -> Self {
+        Self {
+            escrow,
+            token_program: spl_token::ID,
+            remaining_accounts: Vec::new(),
+        }
+    }
+
+    pub fn token_program(mut self, token_program: Pubkey) -> Self {
+        self.token_program = token_program;
+        self
+    }
+
+    // Extra accounts required by a transfer hook on token A
+    pub fn remaining_accounts(mut self, remaining_accounts: Vec<AccountMeta>) -> Self {
+        self.remaining_accounts = remaining_accounts;
+        self
+    }
+
+    // Build with the maker as signer
+    pub fn instruction(&self) -> Instruction {
+        self.build(true)
+    }
+
+    // Build for anyone to crank once the escrow has expired
+    pub fn expired_instruction(&self) -> Instruction {
+        self.build(false)
+    }
+
+    fn build(&self, maker_signs: bool) -> Instruction {
+        let Escrow {
+            seed,
+            maker,
+            mint_a,
+            registry,
+            ..
+        } = self.escrow;
+        let escrow = find_escrow_address(&maker, seed).0;
+        let mut accounts = vec![
+            AccountMeta::new(maker, maker_signs),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new(token_account(&maker, &mint_a, &self.token_program), false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(find_vault_address(&escrow).0, false),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            registry_account(registry),
+        ];
+        accounts.extend_from_slice(&self.remaining_accounts);
+        Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: EscrowInstructions::Refund.pack(),
+        }
+    }
+}
+
+pub fn find_bundle_address(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bundle", maker.as_ref(), seed.to_le_bytes().as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_bundle_vault_address(bundle: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bundle_vault", bundle.as_ref(), mint.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn unpack_bundle(data: &[u8]) -> Result<Bundle, ProgramError> {
+    bytemuck::try_pod_read_unaligned::<Bundle>(data).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BundleLeg {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub amount: u64,
+}
+
+// Offer every `offers` leg for every `asks` leg; each side takes 1 to MAX_BUNDLE_LEGS legs
+pub fn make_bundle(
+    maker: Pubkey,
+    seed: u64,
+    offers: &[BundleLeg],
+    asks: &[BundleLeg],
+    expiry: i64,
+    taker: Pubkey,
+) -> Instruction {
+    let bundle = find_bundle_address(&maker, seed).0;
+    let mut args = MakeBundle {
+        seed,
+        expiry,
+        taker,
+        offer_amounts: [0; MAX_BUNDLE_LEGS],
+        ask_amounts: [0; MAX_BUNDLE_LEGS],
+        offer_count: offers.len() as u8,
+        ask_count: asks.len() as u8,
+        _padding: [0; 6],
+    };
+    let mut accounts = vec![
+        AccountMeta::new(maker, true),
+        AccountMeta::new(bundle, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    for (i, leg) in offers.iter().enumerate().take(MAX_BUNDLE_LEGS) {
+        args.offer_amounts[i] = leg.amount;
+        accounts.extend([
+            AccountMeta::new_readonly(leg.mint, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(&maker, &leg.mint, &leg.token_program),
+                false,
+            ),
+            AccountMeta::new(find_bundle_vault_address(&bundle, &leg.mint).0, false),
+            AccountMeta::new_readonly(leg.token_program, false),
+        ]);
+    }
+    for (i, leg) in asks.iter().enumerate().take(MAX_BUNDLE_LEGS) {
+        args.ask_amounts[i] = leg.amount;
+        accounts.extend([
+            AccountMeta::new_readonly(leg.mint, false),
+            AccountMeta::new_readonly(leg.token_program, false),
+        ]);
+    }
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data: EscrowInstructions::MakeBundle(args).pack(),
+    }
+}
+
+// Fill `bundle`, as read with `unpack_bundle`. Token programs are given per leg in
+// the bundle's order. Fees go to the treasury's associated token accounts.
+pub fn take_bundle(
+    taker: Pubkey,
+    bundle: &Bundle,
+    offer_token_programs: &[Pubkey],
+    ask_token_programs: &[Pubkey],
+    treasury: Option<Pubkey>,
+) -> Instruction {
+    let address = find_bundle_address(&bundle.maker, bundle.seed).0;
+    let config = find_config_address().0;
+    // Unused until a fee is configured, so the config stands in for it
+    let treasury_account = |mint: &Pubkey, token_program: &Pubkey| match treasury {
+        Some(treasury) => AccountMeta::new(
+            get_associated_token_address_with_program_id(&treasury, mint, token_program),
+            false,
+        ),
+        None => AccountMeta::new_readonly(config, false),
+    };
+    let mut accounts = vec![
+        AccountMeta::new(taker, true),
+        AccountMeta::new(bundle.maker, false),
+        AccountMeta::new(address, false),
+        AccountMeta::new_readonly(config, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(spl_associated_token_account_client::program::ID, false),
+    ];
+    for (leg, token_program) in bundle.offers().iter().zip(offer_token_programs) {
+        accounts.extend([
+            AccountMeta::new_readonly(leg.mint, false),
+            AccountMeta::new(find_bundle_vault_address(&address, &leg.mint).0, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(&taker, &leg.mint, token_program),
+                false,
+            ),
+            treasury_account(&leg.mint, token_program),
+            AccountMeta::new_readonly(*token_program, false),
+        ]);
+    }
+    for (leg, token_program) in bundle.asks().iter().zip(ask_token_programs) {
+        accounts.extend([
+            AccountMeta::new_readonly(leg.mint, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(&taker, &leg.mint, token_program),
+                false,
+            ),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    &bundle.maker,
+                    &leg.mint,
+                    token_program,
+                ),
+                false,
+            ),
+            treasury_account(&leg.mint, token_program),
+            AccountMeta::new_readonly(*token_program, false),
+        ]);
+    }
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data: EscrowInstructions::TakeBundle.pack(),
+    }
+}
+
+// Return a bundle's deposits to its maker, who must sign unless the offer has expired
+pub fn refund_bundle(
+    bundle: &Bundle,
+    offer_token_programs: &[Pubkey],
+    maker_signs: bool,
+) -> Instruction {
+    let address = find_bundle_address(&bundle.maker, bundle.seed).0;
+    let mut accounts = vec![
+        AccountMeta::new(bundle.maker, maker_signs),
+        AccountMeta::new(address, false),
+    ];
+    for (leg, token_program) in bundle.offers().iter().zip(offer_token_programs) {
+        accounts.extend([
+            AccountMeta::new_readonly(leg.mint, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    &bundle.maker,
+                    &leg.mint,
+                    token_program,
+                ),
+                false,
+            ),
+            AccountMeta::new(find_bundle_vault_address(&address, &leg.mint).0, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ]);
+    }
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data: EscrowInstructions::RefundBundle.pack(),
+    }
+}
+
+pub fn find_service_address(buyer: &Pubkey, seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"service", buyer.as_ref(), seed.to_le_bytes().as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn unpack_service(data: &[u8]) -> Result<Service, ProgramError> {
+    bytemuck::try_pod_read_unaligned::<Service>(data).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+pub fn make_service(
+    buyer: Pubkey,
+    seller: Pubkey,
+    arbiter: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    seed: u64,
+    amount: u64,
+) -> Instruction {
+    let service = find_service_address(&buyer, seed).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(buyer, true),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(token_account(&buyer, &mint, &token_program), false),
+            AccountMeta::new(service, false),
+            AccountMeta::new(find_vault_address(&service).0, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: EscrowInstructions::MakeService(MakeService {
+            seed,
+            amount,
+            seller,
+            arbiter,
+        })
+        .pack(),
+    }
+}
+
+// Accounts shared by ApproveService and SettleService
+fn service_payout_accounts(
+    service: &Service,
+    token_program: Pubkey,
+    buyer_signs: bool,
+) -> Vec<AccountMeta> {
+    let address = find_service_address(&service.buyer, service.seed).0;
+    vec![
+        AccountMeta::new(service.buyer, buyer_signs),
+        AccountMeta::new(service.seller, false),
+        AccountMeta::new_readonly(service.mint, false),
+        AccountMeta::new(
+            token_account(&service.buyer, &service.mint, &token_program),
+            false,
+        ),
+        AccountMeta::new(
+            token_account(&service.seller, &service.mint, &token_program),
+            false,
+        ),
+        AccountMeta::new(address, false),
+        AccountMeta::new(find_vault_address(&address).0, false),
+        AccountMeta::new_readonly(token_program, false),
+    ]
+}
+
+// Release the whole deposit of `service`, as read with `unpack_service`, to the seller
+pub fn approve_service(service: &Service, token_program: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: service_payout_accounts(service, token_program, true),
+        data: EscrowInstructions::ApproveService.pack(),
+    }
+}
+
+pub fn dispute_service(party: Pubkey, service: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(party, true),
+            AccountMeta::new(service, false),
+        ],
+        data: EscrowInstructions::DisputeService.pack(),
+    }
+}
+
+pub fn resolve_service(arbiter: Pubkey, service: Pubkey, seller_amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(arbiter, true),
+            AccountMeta::new(service, false),
+        ],
+        data: EscrowInstructions::ResolveService(ResolveService { seller_amount }).pack(),
+    }
+}
+
+// Pay out the arbiter's ruling; needs no signature
+pub fn settle_service(service: &Service, token_program: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: service_payout_accounts(service, token_program, false),
+        data: EscrowInstructions::SettleService.pack(),
+    }
+}
+
+pub fn find_vesting_address(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"vesting", maker.as_ref(), seed.to_le_bytes().as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn unpack_vesting(data: &[u8]) -> Result<Vesting, ProgramError> {
+    bytemuck::try_pod_read_unaligned::<Vesting>(data).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// Vest `amount` to `beneficiary` between `start` and `end`, nothing before `cliff`
+pub fn make_vesting(
+    maker: Pubkey,
+    beneficiary: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    seed: u64,
+    amount: u64,
+    start: i64,
+    cliff: i64,
+    end: i64,
+    revocable: bool,
+) -> Instruction {
+    let vesting = find_vesting_address(&maker, seed).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(maker, true),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(token_account(&maker, &mint, &token_program), false),
+            AccountMeta::new(vesting, false),
+            AccountMeta::new(find_vault_address(&vesting).0, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: EscrowInstructions::MakeVesting(MakeVesting {
+            seed,
+            amount,
+            beneficiary,
+            start,
+            cliff,
+            end,
+            revocable: revocable as u8,
+            _padding: [0; 7],
+        })
+        .pack(),
+    }
+}
+
+// Claim what has vested of `vesting`, as read with `unpack_vesting`
+pub fn claim_vesting(vesting: &Vesting, token_program: Pubkey) -> Instruction {
+    let address = find_vesting_address(&vesting.maker, vesting.seed).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vesting.beneficiary, true),
+            AccountMeta::new(vesting.maker, false),
+            AccountMeta::new_readonly(vesting.mint, false),
+            AccountMeta::new(
+                token_account(&vesting.beneficiary, &vesting.mint, &token_program),
+                false,
+            ),
+            AccountMeta::new(address, false),
+            AccountMeta::new(find_vault_address(&address).0, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+        data: EscrowInstructions::ClaimVesting.pack(),
+    }
+}
+
+pub fn revoke_vesting(vesting: &Vesting, token_program: Pubkey) -> Instruction {
+    let address = find_vesting_address(&vesting.maker, vesting.seed).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vesting.maker, true),
+            AccountMeta::new_readonly(vesting.mint, false),
+            AccountMeta::new(
+                token_account(&vesting.maker, &vesting.mint, &token_program),
+                false,
+            ),
+            AccountMeta::new(address, false),
+            AccountMeta::new(find_vault_address(&address).0, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+        data: EscrowInstructions::RevokeVesting.pack(),
+    }
+}
+
+pub fn find_counter_offer_address(escrow: &Pubkey, taker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"counter", escrow.as_ref(), taker.as_ref()], &crate::ID)
+}
+
+pub fn unpack_counter_offer(data: &[u8]) -> Result<CounterOffer, ProgramError> {
+    bytemuck::try_pod_read_unaligned::<CounterOffer>(data)
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// Offer `amount_b` of `escrow`'s token B for `amount_a` of its token A
+pub fn make_counter_offer(
+    taker: Pubkey,
+    escrow: &Escrow,
+    token_program_b: Pubkey,
+    amount_a: u64,
+    amount_b: u64,
+) -> Instruction {
+    let address = find_escrow_address(&escrow.maker, escrow.seed).0;
+    let counter = find_counter_offer_address(&address, &taker).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(taker, true),
+            AccountMeta::new_readonly(escrow.mint_b, false),
+            AccountMeta::new(
+                token_account(&taker, &escrow.mint_b, &token_program_b),
+                false,
+            ),
+            AccountMeta::new_readonly(address, false),
+            AccountMeta::new(counter, false),
+            AccountMeta::new(find_vault_address(&counter).0, false),
+            AccountMeta::new_readonly(token_program_b, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: EscrowInstructions::MakeCounterOffer(MakeCounterOffer { amount_a, amount_b }).pack(),
+    }
+}
+
+// Accept `counter` against `escrow`, as read with `unpack_counter_offer` and
+// `unpack_escrow`. `treasury_ta` is as for `TakeBuilder::treasury_ta`.
+pub fn accept_counter_offer(
+    escrow: &Escrow,
+    counter: &CounterOffer,
+    token_program_a: Pubkey,
+    token_program_b: Pubkey,
+    treasury_ta: Option<Pubkey>,
+) -> Instruction {
+    let address = find_counter_offer_address(&counter.escrow, &counter.taker).0;
+    let config = find_config_address().0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(escrow.maker, true),
+            AccountMeta::new(counter.taker, false),
+            AccountMeta::new_readonly(escrow.mint_a, false),
+            AccountMeta::new_readonly(escrow.mint_b, false),
+            AccountMeta::new(
+                token_account(&escrow.maker, &escrow.mint_b, &token_program_b),
+                false,
+            ),
+            AccountMeta::new(
+                token_account(&counter.taker, &escrow.mint_a, &token_program_a),
+                false,
+            ),
+            AccountMeta::new(counter.escrow, false),
+            AccountMeta::new(find_vault_address(&counter.escrow).0, false),
+            AccountMeta::new(address, false),
+            AccountMeta::new(find_vault_address(&address).0, false),
+            AccountMeta::new_readonly(token_program_a, false),
+            AccountMeta::new_readonly(token_program_b, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(spl_associated_token_account_client::program::ID, false),
+            AccountMeta::new_readonly(config, false),
+            // Unused until a fee is configured, so the config stands in for it
+            match treasury_ta {
+                Some(treasury_ta) => AccountMeta::new(treasury_ta, false),
+                None => AccountMeta::new_readonly(config, false),
+            },
+            registry_account(escrow.registry),
+        ],
+        data: EscrowInstructions::AcceptCounterOffer.pack(),
+    }
+}
+
+// Withdraw `counter` as its taker, or reject it as the escrow's maker
+pub fn withdraw_counter_offer(
+    authority: Pubkey,
+    counter: &CounterOffer,
+    token_program: Pubkey,
+) -> Instruction {
+    let address = find_counter_offer_address(&counter.escrow, &counter.taker).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(counter.taker, false),
+            AccountMeta::new_readonly(counter.mint_b, false),
+            AccountMeta::new(
+                token_account(&counter.taker, &counter.mint_b, &token_program),
+                false,
+            ),
+            AccountMeta::new_readonly(counter.escrow, false),
+            AccountMeta::new(address, false),
+            AccountMeta::new(find_vault_address(&address).0, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+        data: EscrowInstructions::WithdrawCounterOffer.pack(),
+    }
+}
+
+// Match `x` against `y`, as read with `unpack_escrow`, where `x` offers mint A
+// for mint B and `y` the reverse, signed by `cranker`. With `bounty` the cranker
+// collects any surplus in their existing associated token accounts; without it
+// the surplus improves the other maker's price. Fees go to the `treasury`
+// wallet's associated token accounts.
+pub fn match_escrows(
+    cranker: Pubkey,
+    x: &Escrow,
+    y: &Escrow,
+    token_program_a: Pubkey,
+    token_program_b: Pubkey,
+    bounty: bool,
+    treasury: Option<Pubkey>,
+) -> Instruction {
+    let config = find_config_address().0;
+    let side = |escrow: &Escrow, token_program: Pubkey, other_token_program: Pubkey| {
+        let address = find_escrow_address(&escrow.maker, escrow.seed).0;
+        [
+            AccountMeta::new(escrow.maker, false),
+            AccountMeta::new_readonly(escrow.mint_a, false),
+            AccountMeta::new(address, false),
+            AccountMeta::new(find_vault_address(&address).0, false),
+            AccountMeta::new(
+                token_account(&escrow.maker, &escrow.mint_b, &other_token_program),
+                false,
+            ),
+            if bounty {
+                AccountMeta::new(
+                    token_account(&cranker, &escrow.mint_a, &token_program),
+                    false,
+                )
+            } else {
+                AccountMeta::new_readonly(crate::ID, false)
+            },
+            // Unused until a fee is configured, so the config stands in for it
+            match treasury {
+                Some(treasury) => AccountMeta::new(
+                    token_account(&treasury, &escrow.mint_a, &token_program),
+                    false,
+                ),
+                None => AccountMeta::new_readonly(config, false),
+            },
+            AccountMeta::new_readonly(token_program, false),
+            registry_account(escrow.registry),
+        ]
+    };
+    let mut accounts = vec![
+        AccountMeta::new_readonly(cranker, true),
+        AccountMeta::new_readonly(config, false),
+    ];
+    accounts.extend(side(x, token_program_a, token_program_b));
+    accounts.extend(side(y, token_program_b, token_program_a));
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data: EscrowInstructions::Match.pack(),
+    }
+}
@@ -0,0 +1,41 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::{CounterOffer, Escrow};
+
+//fill the escrow on the taker's proposed terms
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [maker, taker, mint_a, mint_b, maker_ta_b, taker_ta_a, escrow, vault, counter, counter_vault, token_program_a, token_program_b, system_program, _associated_token_program, config, treasury_ta, registry, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (escrow_data, escrow_bump) = Escrow::get_data_and_bump(maker.key, escrow)?;
+    let (counter_data, counter_bump) = CounterOffer::get_data_and_bump(counter)?;
+
+    CounterOffer::accept(
+        counter_data,
+        counter_bump,
+        escrow_data,
+        escrow_bump,
+        token_program_a,
+        token_program_b,
+        maker,
+        taker,
+        mint_a,
+        mint_b,
+        maker_ta_b,
+        taker_ta_a,
+        escrow,
+        vault,
+        counter,
+        counter_vault,
+        system_program,
+        config,
+        treasury_ta,
+        registry,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,41 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{
+    error::EscrowError,
+    states::{Service, SERVICE_RESOLVED},
+};
+
+//the buyer releases the whole deposit to the seller, even mid-dispute
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [buyer, seller, mint, buyer_ta, seller_ta, service, vault, token_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (service_data, bump) = Service::get_data_and_bump(service)?;
+    if !buyer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // Once the arbiter has ruled, only its split can be paid out
+    if service_data.status == SERVICE_RESOLVED {
+        return Err(EscrowError::InvalidServiceStatus.into());
+    }
+
+    Service::payout(
+        service_data,
+        bump,
+        service_data.amount,
+        token_program.key,
+        buyer,
+        seller,
+        mint,
+        buyer_ta,
+        seller_ta,
+        service,
+        vault,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,30 @@
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+use crate::states::Vesting;
+
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [beneficiary, maker, mint, beneficiary_ta, vesting, vault, token_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (vesting_data, bump) = Vesting::get_data_and_bump(maker.key, vesting)?;
+
+    Vesting::claim(
+        vesting_data,
+        bump,
+        Clock::get()?.unix_timestamp,
+        token_program.key,
+        beneficiary,
+        maker,
+        mint,
+        beneficiary_ta,
+        vesting,
+        vault,
+        remaining_accounts,
+    )
+}
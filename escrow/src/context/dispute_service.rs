@@ -0,0 +1,14 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::Service;
+
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [party, service] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (service_data, _) = Service::get_data_and_bump(service)?;
+    Service::dispute(service_data, party, service)
+}
@@ -0,0 +1,38 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::Config;
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct ConfigArgs {
+    pub fee_bps: u16,
+    pub fee_leg: u8,
+}
+impl TryFrom<&[u8]> for ConfigArgs {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//create the protocol fee config, owned by the program's upgrade authority
+pub fn process(accounts: &[AccountInfo<'_>], args: ConfigArgs) -> ProgramResult {
+    let ConfigArgs { fee_bps, fee_leg } = args;
+
+    let [admin, config, treasury, program_data, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Config::check_upgrade_authority(admin, program_data)?;
+
+    Config::init(fee_bps, fee_leg, admin, treasury.key, config)
+}
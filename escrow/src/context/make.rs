@@ -1,16 +1,26 @@
 use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
 };
 
-use crate::states::Escrow;
+use crate::{
+    events::{Event, MakeEvent},
+    states::{check_token_program, is_native, Escrow, OraclePricing, RegistryPage},
+};
 
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
 pub struct Make {
     pub seed: u64,
     pub amount: u64,
     pub receive: u64,
+    pub expiry: i64,
+    // Designated taker, default for an open offer
+    pub taker: Pubkey,
+    // Follow a price feed instead of the fixed `receive` ask
+    pub pricing: OraclePricing,
 }
 impl TryFrom<&[u8]> for Make {
     type Error = ProgramError;
@@ -22,29 +32,92 @@ impl TryFrom<&[u8]> for Make {
 }
 
 //deposit funds into vault derived from Maker's pubkey and seeds
-pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+pub fn process(accounts: &[AccountInfo<'_>], args: Make) -> ProgramResult {
     let Make {
         seed,
         amount,
         receive,
-    } = Make::try_from(data)?;
+        expiry,
+        taker,
+        pricing,
+    } = args;
 
-    let [maker, mint_a, mint_b, maker_ta_a, escrow, vault, token_program, _system_program] =
+    let [maker, mint_a, mint_b, maker_ta_a, escrow, vault, token_program_a, token_program_b, _system_program, registry, remaining_accounts @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    // Token B's program is only used at Take, but make sure the offer can be filled
+    check_token_program(token_program_b.key, mint_b)?;
+
+    //deposit funds to vault, tracking what actually landed after transfer fees.
+    //Native SOL is held by the escrow itself, so it is deposited once the escrow exists
+    let native = is_native(mint_a.key);
+    let (deposited, vault_bump) = if native {
+        (amount, 0)
+    } else {
+        let vault_bump =
+            Escrow::create_vault(escrow.key, token_program_a.key, mint_a, vault, maker)?;
+        let deposited = Escrow::deposit(
+            escrow,
+            token_program_a.key,
+            amount,
+            maker_ta_a,
+            mint_a,
+            vault,
+            maker,
+            remaining_accounts,
+        )?;
+        (deposited, vault_bump)
+    };
+    //list the offer on its mint pair's registry page, if one was given
+    let listed = !RegistryPage::is_none(registry);
+    if listed {
+        RegistryPage::insert(registry, mint_a.key, mint_b.key, escrow.key)?;
+    }
     //initialize escrow account and data
-    Escrow::init(seed, receive, *mint_a.key, *mint_b.key, &maker, &escrow)?;
-    //deposit funds to vault
-    Escrow::deposit(
-        escrow.key,
-        token_program.key,
-        amount,
-        &maker_ta_a,
-        &mint_a,
-        &vault,
-        &maker,
-    )
+    Escrow::init(
+        seed,
+        deposited,
+        receive,
+        expiry,
+        taker,
+        *mint_a.key,
+        *mint_b.key,
+        if listed {
+            *registry.key
+        } else {
+            Pubkey::default()
+        },
+        pricing,
+        vault_bump,
+        maker,
+        escrow,
+    )?;
+    if native {
+        Escrow::deposit(
+            escrow,
+            token_program_a.key,
+            amount,
+            maker_ta_a,
+            mint_a,
+            vault,
+            maker,
+            remaining_accounts,
+        )?;
+    }
+
+    MakeEvent {
+        escrow: *escrow.key,
+        maker: *maker.key,
+        mint_a: *mint_a.key,
+        mint_b: *mint_b.key,
+        taker,
+        amount: deposited,
+        receive,
+        expiry,
+    }
+    .emit();
+    Ok(())
 }
@@ -0,0 +1,69 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::states::{Bundle, MAKE_ASK_ACCOUNTS, MAKE_OFFER_ACCOUNTS, MAX_BUNDLE_LEGS};
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct MakeBundle {
+    pub seed: u64,
+    pub expiry: i64,
+    // Designated taker, default for an open offer
+    pub taker: Pubkey,
+    // Only the first `offer_count` and `ask_count` entries are used
+    pub offer_amounts: [u64; MAX_BUNDLE_LEGS],
+    pub ask_amounts: [u64; MAX_BUNDLE_LEGS],
+    pub offer_count: u8,
+    pub ask_count: u8,
+    pub _padding: [u8; 6],
+}
+impl TryFrom<&[u8]> for MakeBundle {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//deposit every offered token into its own vault under a new bundle
+pub fn process(accounts: &[AccountInfo<'_>], args: MakeBundle) -> ProgramResult {
+    let MakeBundle {
+        seed,
+        expiry,
+        taker,
+        offer_amounts,
+        ask_amounts,
+        offer_count,
+        ask_count,
+        ..
+    } = args;
+    Bundle::check_leg_counts(offer_count, ask_count)?;
+
+    let [maker, bundle, _system_program, legs @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let (offer_accounts, legs) = legs
+        .split_at_checked(offer_count as usize * MAKE_OFFER_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (ask_accounts, remaining_accounts) = legs
+        .split_at_checked(ask_count as usize * MAKE_ASK_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    Bundle::init(
+        seed,
+        expiry,
+        taker,
+        &offer_amounts[..offer_count as usize],
+        &ask_amounts[..ask_count as usize],
+        maker,
+        bundle,
+        offer_accounts,
+        ask_accounts,
+        remaining_accounts,
+    )
+}
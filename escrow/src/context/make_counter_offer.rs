@@ -0,0 +1,48 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::CounterOffer;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct MakeCounterOffer {
+    // Token A asked for out of the escrow
+    pub amount_a: u64,
+    // Token B deposited in return
+    pub amount_b: u64,
+}
+impl TryFrom<&[u8]> for MakeCounterOffer {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//propose different terms for an escrow, backed by the taker's token B
+pub fn process(accounts: &[AccountInfo<'_>], args: MakeCounterOffer) -> ProgramResult {
+    let MakeCounterOffer { amount_a, amount_b } = args;
+
+    let [taker, mint_b, taker_ta_b, escrow, counter, vault, token_program, _system_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    CounterOffer::init(
+        amount_a,
+        amount_b,
+        token_program.key,
+        taker,
+        taker_ta_b,
+        mint_b,
+        escrow,
+        counter,
+        vault,
+        remaining_accounts,
+    )
+}
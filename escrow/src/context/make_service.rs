@@ -0,0 +1,55 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::states::Service;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct MakeService {
+    pub seed: u64,
+    pub amount: u64,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+}
+impl TryFrom<&[u8]> for MakeService {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//hold the buyer's payment until they approve the seller's work or the arbiter rules
+pub fn process(accounts: &[AccountInfo<'_>], args: MakeService) -> ProgramResult {
+    let MakeService {
+        seed,
+        amount,
+        seller,
+        arbiter,
+    } = args;
+
+    let [buyer, mint, buyer_ta, service, vault, token_program, _system_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    Service::init(
+        seed,
+        amount,
+        seller,
+        arbiter,
+        token_program.key,
+        buyer,
+        buyer_ta,
+        mint,
+        service,
+        vault,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,68 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::states::Vesting;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct MakeVesting {
+    pub seed: u64,
+    pub amount: u64,
+    pub beneficiary: Pubkey,
+    // Unix timestamps; set `cliff` to `start` for a plain linear schedule
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+    // Non-zero to let the maker revoke the unvested portion
+    pub revocable: u8,
+    pub _padding: [u8; 7],
+}
+impl TryFrom<&[u8]> for MakeVesting {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//lock the maker's deposit to vest to the beneficiary over time
+pub fn process(accounts: &[AccountInfo<'_>], args: MakeVesting) -> ProgramResult {
+    let MakeVesting {
+        seed,
+        amount,
+        beneficiary,
+        start,
+        cliff,
+        end,
+        revocable,
+        ..
+    } = args;
+
+    let [maker, mint, maker_ta, vesting, vault, token_program, _system_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    Vesting::init(
+        seed,
+        amount,
+        beneficiary,
+        start,
+        cliff,
+        end,
+        revocable != 0,
+        token_program.key,
+        maker,
+        maker_ta,
+        mint,
+        vesting,
+        vault,
+        remaining_accounts,
+    )
+}
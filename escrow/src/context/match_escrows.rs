@@ -0,0 +1,53 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::{cross, Escrow, MatchSide};
+
+//settle two crossing escrows against each other; anyone can crank it
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [cranker, config, maker_x, mint_a, escrow_x, vault_x, maker_x_ta_b, bounty_ta_a, treasury_ta_a, token_program_a, registry_x, maker_y, mint_b, escrow_y, vault_y, maker_y_ta_a, bounty_ta_b, treasury_ta_b, token_program_b, registry_y, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !cranker.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (escrow_x_data, bump_x) = Escrow::get_data_and_bump(maker_x.key, escrow_x)?;
+    let (escrow_y_data, bump_y) = Escrow::get_data_and_bump(maker_y.key, escrow_y)?;
+
+    cross(
+        cranker,
+        config,
+        MatchSide {
+            escrow_data: escrow_x_data,
+            bump: bump_x,
+            maker: maker_x,
+            mint: mint_a,
+            escrow: escrow_x,
+            vault: vault_x,
+            maker_ta: maker_x_ta_b,
+            bounty_ta: bounty_ta_a,
+            treasury_ta: treasury_ta_a,
+            token_program: token_program_a,
+            registry: registry_x,
+        },
+        MatchSide {
+            escrow_data: escrow_y_data,
+            bump: bump_y,
+            maker: maker_y,
+            mint: mint_b,
+            escrow: escrow_y,
+            vault: vault_y,
+            maker_ta: maker_y_ta_a,
+            bounty_ta: bounty_ta_b,
+            treasury_ta: treasury_ta_b,
+            token_program: token_program_b,
+            registry: registry_y,
+        },
+        remaining_accounts,
+    )
+}
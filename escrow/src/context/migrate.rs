@@ -0,0 +1,41 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::states::Escrow;
+
+// Terms of an escrow in the legacy layout, restated by its maker since Make
+// sized those accounts but never wrote them. Later layouts carry their own.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct Migrate {
+    pub seed: u64,
+    pub receive: u64,
+    pub mint_b: Pubkey,
+}
+impl TryFrom<&[u8]> for Migrate {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // Version 1 escrows need no terms, so their migrations carry no data
+        if value.is_empty() {
+            return Ok(Self::zeroed());
+        }
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+pub fn process(accounts: &[AccountInfo<'_>], args: Migrate) -> ProgramResult {
+    let [payer, escrow, vault, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Escrow::migrate(payer, escrow, vault, args)
+}
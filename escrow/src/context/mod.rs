@@ -1,7 +1,36 @@
+pub mod accept_counter_offer;
+pub mod approve_service;
+pub mod claim_vesting;
+pub mod dispute_service;
+pub mod init_config;
 pub mod make;
+pub mod make_bundle;
+pub mod make_counter_offer;
+pub mod make_service;
+pub mod make_vesting;
+pub mod match_escrows;
+pub mod migrate;
+pub mod open_registry_page;
 pub mod refund;
+pub mod refund_bundle;
+pub mod resolve_service;
+pub mod revoke_vesting;
+pub mod settle_service;
 pub mod take;
+pub mod take_bundle;
+pub mod update;
+pub mod update_config;
+pub mod withdraw_counter_offer;
 
-pub use make::*;
-pub use refund::*;
-pub use take::*;
+// Instruction data types; handlers are called by module path
+pub use init_config::ConfigArgs;
+pub use make::Make;
+pub use make_bundle::MakeBundle;
+pub use make_counter_offer::MakeCounterOffer;
+pub use make_service::MakeService;
+pub use make_vesting::MakeVesting;
+pub use migrate::Migrate;
+pub use open_registry_page::OpenRegistryPage;
+pub use resolve_service::ResolveService;
+pub use take::{Take, TakePartial};
+pub use update::Update;
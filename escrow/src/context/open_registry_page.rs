@@ -0,0 +1,36 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::RegistryPage;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct OpenRegistryPage {
+    pub page: u32,
+}
+impl TryFrom<&[u8]> for OpenRegistryPage {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//open another page of the registry for a mint pair, paid for by anyone
+pub fn process(accounts: &[AccountInfo<'_>], args: OpenRegistryPage) -> ProgramResult {
+    let OpenRegistryPage { page } = args;
+
+    let [payer, mint_a, mint_b, registry, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    RegistryPage::open(page, payer, mint_a.key, mint_b.key, registry)
+}
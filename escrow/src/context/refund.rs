@@ -1,19 +1,25 @@
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, sysvar::Sysvar,
 };
 
 use crate::states::Escrow;
 
 pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
-    let [maker, mint_a, maker_ta_a, escrow, vault, token_program, _system_program] = accounts
+    let [maker, mint_a, maker_ta_a, escrow, vault, token_program, _system_program, registry, remaining_accounts @ ..] =
+        accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    assert!(maker.is_signer);
     let (escrow_data, bump) = Escrow::get_data_and_bump(maker.key, escrow)?;
 
-    // Take: Claim token A to taker, Transfer token B to maker, Close the vault & escrow
+    // Only the maker can refund a live offer; once expired anyone can crank it
+    if !maker.is_signer && !escrow_data.is_expired(Clock::get()?.unix_timestamp) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Refund: Return token A and rent to maker, Close the vault & escrow
     Escrow::refund(
         escrow_data,
         bump,
@@ -23,5 +29,7 @@ pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
         escrow,
         vault,
         maker,
+        registry,
+        remaining_accounts,
     )
 }
@@ -0,0 +1,32 @@
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+use crate::states::{Bundle, REFUND_OFFER_ACCOUNTS};
+
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [maker, bundle, legs @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (bundle_data, bump) = Bundle::get_data_and_bump(maker.key, bundle)?;
+
+    // Only the maker can refund a live offer; once expired anyone can crank it
+    if !maker.is_signer && !bundle_data.is_expired(Clock::get()?.unix_timestamp) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (offer_accounts, remaining_accounts) = legs
+        .split_at_checked(bundle_data.offers().len() * REFUND_OFFER_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    Bundle::refund(
+        bundle_data,
+        bump,
+        maker,
+        bundle,
+        offer_accounts,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,31 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::Service;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct ResolveService {
+    // Token awarded to the seller, the rest of the deposit going back to the buyer
+    pub seller_amount: u64,
+}
+impl TryFrom<&[u8]> for ResolveService {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+pub fn process(accounts: &[AccountInfo<'_>], args: ResolveService) -> ProgramResult {
+    let [arbiter, service] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (service_data, _) = Service::get_data_and_bump(service)?;
+    Service::resolve(service_data, args.seller_amount, arbiter, service)
+}
@@ -0,0 +1,28 @@
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+use crate::states::Vesting;
+
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [maker, mint, maker_ta, vesting, vault, token_program, remaining_accounts @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (vesting_data, bump) = Vesting::get_data_and_bump(maker.key, vesting)?;
+
+    Vesting::revoke(
+        vesting_data,
+        bump,
+        Clock::get()?.unix_timestamp,
+        token_program.key,
+        maker,
+        mint,
+        maker_ta,
+        vesting,
+        vault,
+        remaining_accounts,
+    )
+}
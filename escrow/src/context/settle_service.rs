@@ -0,0 +1,37 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{
+    error::EscrowError,
+    states::{Service, SERVICE_RESOLVED},
+};
+
+//pay out the arbiter's ruling; anyone can crank it
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [buyer, seller, mint, buyer_ta, seller_ta, service, vault, token_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (service_data, bump) = Service::get_data_and_bump(service)?;
+    if service_data.status != SERVICE_RESOLVED {
+        return Err(EscrowError::InvalidServiceStatus.into());
+    }
+
+    Service::payout(
+        service_data,
+        bump,
+        service_data.seller_amount,
+        token_program.key,
+        buyer,
+        seller,
+        mint,
+        buyer_ta,
+        seller_ta,
+        service,
+        vault,
+        remaining_accounts,
+    )
+}
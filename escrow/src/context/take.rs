@@ -1,11 +1,102 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
 };
 
-use crate::states::Escrow;
+use crate::states::{init_ata_if_needed, Escrow};
 
-pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
-    let [taker, maker, mint_a, mint_b, taker_ta_a, taker_ta_b, maker_ta_b, escrow, vault, token_program, _system_program] =
+// Worst terms the taker accepts, so the maker can't change them under a pending fill
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct Take {
+    // Least token A the taker must receive, after any fee on that leg
+    pub min_amount: u64,
+    // Most token B the taker will pay, including transfer and protocol fees
+    pub max_payment: u64,
+}
+impl Take {
+    pub const ANY: Self = Self {
+        min_amount: 0,
+        max_payment: u64::MAX,
+    };
+}
+impl TryFrom<&[u8]> for Take {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // Takes built before limits existed carry no data and accept any terms
+        if value.is_empty() {
+            return Ok(Self::ANY);
+        }
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct TakePartial {
+    pub amount: u64,
+    // Same limits as a plain Take, for this fill only
+    pub min_amount: u64,
+    pub max_payment: u64,
+}
+impl TakePartial {
+    pub fn limits(&self) -> Take {
+        Take {
+            min_amount: self.min_amount,
+            max_payment: self.max_payment,
+        }
+    }
+}
+impl TryFrom<&[u8]> for TakePartial {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // Partial takes built before limits existed only carry the amount
+        if let Ok(amount) = <[u8; 8]>::try_from(value) {
+            let Take {
+                min_amount,
+                max_payment,
+            } = Take::ANY;
+            return Ok(Self {
+                amount: u64::from_le_bytes(amount),
+                min_amount,
+                max_payment,
+            });
+        }
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+pub fn process(accounts: &[AccountInfo<'_>], args: Take) -> ProgramResult {
+    fill(None, accounts, None, args)
+}
+
+// Fill part of the escrow, paying the maker a pro-rata share of the ask
+pub fn process_partial(accounts: &[AccountInfo<'_>], args: TakePartial) -> ProgramResult {
+    fill(None, accounts, Some(args.amount), args.limits())
+}
+
+// Fill on the taker's behalf, paying from their token B account through the
+// approval they gave the signing relayer. Token A goes to the taker.
+pub fn process_delegated(accounts: &[AccountInfo<'_>], args: TakePartial) -> ProgramResult {
+    let [relayer, accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    fill(Some(relayer), accounts, Some(args.amount), args.limits())
+}
+
+// `relayer` fills for the taker when given, otherwise the taker fills for themselves
+fn fill<'info>(
+    relayer: Option<&AccountInfo<'info>>,
+    accounts: &[AccountInfo<'info>],
+    amount: Option<u64>,
+    limits: Take,
+) -> ProgramResult {
+    let [taker, maker, mint_a, mint_b, taker_ta_a, taker_ta_b, maker_ta_b, escrow, vault, token_program_a, token_program_b, system_program, _associated_token_program, config, treasury_ta, registry, price_feed, remaining_accounts @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -13,19 +104,51 @@ pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
 
     let (escrow_data, bump) = Escrow::get_data_and_bump(maker.key, escrow)?;
 
-    // Take: Claim token A to taker, Transfer token B to maker, Close the vault & escrow
+    // Create the taker's token A and maker's token B accounts if missing, paid for by whoever fills
+    let payer = relayer.unwrap_or(taker);
+    init_ata_if_needed(
+        payer,
+        taker_ta_a,
+        taker,
+        mint_a,
+        system_program,
+        token_program_a,
+    )?;
+    init_ata_if_needed(
+        payer,
+        maker_ta_b,
+        maker,
+        mint_b,
+        system_program,
+        token_program_b,
+    )?;
+
+    // A plain Take fills whatever is left
+    let amount = amount.unwrap_or(escrow_data.remaining);
+
+    // Take: Claim token A to taker, Transfer token B to maker, Close the vault & escrow once filled
     Escrow::take(
         escrow_data,
         bump,
-        token_program.key,
+        amount,
+        limits.min_amount,
+        limits.max_payment,
+        token_program_a.key,
+        token_program_b.key,
         mint_a,
         mint_b,
         vault,
         maker,
         taker,
+        relayer,
         escrow,
         maker_ta_b,
         taker_ta_a,
         taker_ta_b,
+        config,
+        treasury_ta,
+        registry,
+        price_feed,
+        remaining_accounts,
     )
 }
@@ -0,0 +1,36 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::{Bundle, TAKE_ASK_ACCOUNTS, TAKE_OFFER_ACCOUNTS};
+
+//fill a whole bundle in one go
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [taker, maker, bundle, config, system_program, _associated_token_program, legs @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (bundle_data, bump) = Bundle::get_data_and_bump(maker.key, bundle)?;
+
+    let (offer_accounts, legs) = legs
+        .split_at_checked(bundle_data.offers().len() * TAKE_OFFER_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (ask_accounts, remaining_accounts) = legs
+        .split_at_checked(bundle_data.asks().len() * TAKE_ASK_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    Bundle::take(
+        bundle_data,
+        bump,
+        taker,
+        maker,
+        bundle,
+        config,
+        system_program,
+        offer_accounts,
+        ask_accounts,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,71 @@
+use bytemuck::{Pod, Zeroable};
+use shank::ShankType;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::states::Escrow;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct Update {
+    // New ask for the whole remaining deposit
+    pub receive: u64,
+    // Token A to add to the vault
+    pub deposit: u64,
+    // Token A to take back out of the vault
+    pub withdraw: u64,
+    pub expiry: i64,
+    pub taker: Pubkey,
+}
+impl TryFrom<&[u8]> for Update {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        bytemuck::try_pod_read_unaligned::<Self>(value)
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+//amend an open escrow while keeping the same PDA
+pub fn process(accounts: &[AccountInfo<'_>], args: Update) -> ProgramResult {
+    let Update {
+        receive,
+        deposit,
+        withdraw,
+        expiry,
+        taker,
+    } = args;
+
+    let [maker, mint_a, maker_ta_a, escrow, vault, token_program, _system_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !maker.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (escrow_data, bump) = Escrow::get_data_and_bump(maker.key, escrow)?;
+
+    Escrow::update(
+        escrow_data,
+        bump,
+        Escrow {
+            receive,
+            expiry,
+            taker,
+            ..escrow_data
+        },
+        deposit,
+        withdraw,
+        token_program.key,
+        maker_ta_a,
+        mint_a,
+        escrow,
+        vault,
+        maker,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,16 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{context::ConfigArgs, states::Config};
+
+//change the fee, treasury or admin of the protocol fee config
+pub fn process(accounts: &[AccountInfo<'_>], args: ConfigArgs) -> ProgramResult {
+    let ConfigArgs { fee_bps, fee_leg } = args;
+
+    let [admin, config, treasury, new_admin] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    Config::update(fee_bps, fee_leg, admin, new_admin.key, treasury.key, config)
+}
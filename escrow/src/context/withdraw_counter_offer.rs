@@ -0,0 +1,30 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::states::CounterOffer;
+
+//the taker withdraws a counter-offer, or the maker rejects it
+pub fn process(accounts: &[AccountInfo<'_>]) -> ProgramResult {
+    let [authority, taker, mint_b, taker_ta_b, escrow, counter, vault, token_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (counter_data, bump) = CounterOffer::get_data_and_bump(counter)?;
+
+    CounterOffer::withdraw(
+        counter_data,
+        bump,
+        token_program.key,
+        authority,
+        taker,
+        mint_b,
+        taker_ta_b,
+        escrow,
+        counter,
+        vault,
+        remaining_accounts,
+    )
+}
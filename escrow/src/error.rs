@@ -0,0 +1,92 @@
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+// Codes are part of the program's interface: append new variants, never renumber
+#[derive(Error, Debug, Copy, Clone, PartialEq)]
+pub enum EscrowError {
+    #[error("Fill amount must be non-zero and no larger than the remaining deposit.")]
+    InvalidFillAmount = 0,
+    #[error("Escrow has expired and can no longer be taken.")]
+    Expired = 1,
+    #[error("Escrow is reserved for a different taker.")]
+    InvalidTaker = 2,
+    #[error("Fee must be at most 10000 basis points on a known leg.")]
+    InvalidFee = 3,
+    #[error("Signer is not the config admin.")]
+    InvalidAdmin = 4,
+    #[error("Withdrawal must leave part of the deposit in the vault.")]
+    InvalidWithdraw = 5,
+    #[error("Account is not the expected program derived address.")]
+    InvalidPda = 6,
+    #[error("Mint does not match the escrow.")]
+    MintMismatch = 7,
+    #[error("Token program is neither SPL Token nor Token-2022.")]
+    UnsupportedTokenProgram = 8,
+    #[error("Mint is not owned by the given token program.")]
+    TokenProgramMismatch = 9,
+    #[error("Vault token account is not owned by the escrow.")]
+    InvalidVaultOwner = 10,
+    #[error("Token account is not owned by the maker.")]
+    InvalidMakerTokenAccount = 11,
+    #[error("Fee account is not owned by the treasury.")]
+    InvalidTreasury = 12,
+    #[error("Registry page has no free entries.")]
+    RegistryFull = 13,
+    #[error("Registry page does not list this escrow or its mint pair.")]
+    RegistryMismatch = 14,
+    #[error("Bundle legs must be 1 to 4 non-zero amounts of distinct SPL token mints.")]
+    InvalidBundle = 15,
+    #[error("Price feed has not been updated recently enough.")]
+    StalePrice = 16,
+    #[error("Price feed confidence interval is too wide.")]
+    PriceUncertain = 17,
+    #[error("Price feed is not the escrow's or holds no valid price.")]
+    InvalidPriceFeed = 18,
+    #[error("Oracle pricing needs a positive max age and a floor no higher than the ceiling.")]
+    InvalidPricing = 19,
+    #[error("Service escrow is not in a state that allows this.")]
+    InvalidServiceStatus = 20,
+    #[error("Arbiter must be a third party and sign its ruling.")]
+    InvalidArbiter = 21,
+    #[error("Settlement must split at most the deposit between the parties' token accounts.")]
+    InvalidSettlement = 22,
+    #[error("Only the buyer or seller of the service escrow can do this.")]
+    InvalidServiceParty = 23,
+    #[error("Vesting must start before it ends, with the cliff in between.")]
+    InvalidSchedule = 24,
+    #[error("Vesting was created without the right to revoke it.")]
+    NotRevocable = 25,
+    #[error("Nothing has vested since the last claim.")]
+    NothingVested = 26,
+    #[error("Only the vesting's beneficiary can claim.")]
+    InvalidBeneficiary = 27,
+    #[error("Escrow account uses an old layout and must be migrated first.")]
+    OutdatedAccount = 28,
+    #[error("Offer uses a feature the optimized build leaves out.")]
+    Unsupported = 29,
+    #[error("Counter-offer belongs to a different escrow or taker.")]
+    InvalidCounterOffer = 30,
+    #[error("Escrows must be fixed-price offers of opposite mint pairs whose prices cross.")]
+    PricesDoNotCross = 31,
+    #[error("Token accounts are not the taker's or have not approved this fill's authority.")]
+    InvalidDelegate = 32,
+    #[error("Fill would give less token A or cost more token B than the taker allowed.")]
+    SlippageExceeded = 33,
+    #[error("Token account is not owned by the taker.")]
+    InvalidTakerTokenAccount = 34,
+    #[error("Fixed-price escrows must ask for some token B.")]
+    InvalidReceive = 35,
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+#[cfg(feature = "optimized")]
+impl From<EscrowError> for pinocchio::program_error::ProgramError {
+    fn from(e: EscrowError) -> Self {
+        pinocchio::program_error::ProgramError::Custom(e as u32)
+    }
+}
@@ -0,0 +1,149 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+
+// Events are logged with `sol_log_data` as two fields: a `[version, tag]`
+// header and the event's bytes. Bump the version whenever a layout changes.
+pub const EVENT_VERSION: u8 = 1;
+
+pub trait Event: Pod {
+    const TAG: u8;
+
+    fn emit(&self) {
+        sol_log_data(&[&[EVENT_VERSION, Self::TAG], bytemuck::bytes_of(self)]);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct MakeEvent {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub taker: Pubkey,
+    // Token A that landed in the vault
+    pub amount: u64,
+    pub receive: u64,
+    pub expiry: i64,
+}
+impl Event for MakeEvent {
+    const TAG: u8 = 0;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct TakeEvent {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    // Token A released from the vault, including any fee on that leg
+    pub amount: u64,
+    // Token B owed to the maker for this fill
+    pub paid: u64,
+    pub fee_a: u64,
+    pub fee_b: u64,
+    // Token A left in the escrow, 0 once it is closed
+    pub remaining: u64,
+}
+impl Event for TakeEvent {
+    const TAG: u8 = 1;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct RefundEvent {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    // Token A returned to the maker
+    pub amount: u64,
+}
+impl Event for RefundEvent {
+    const TAG: u8 = 2;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct UpdateEvent {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    // Token A added to and taken out of the vault
+    pub deposit: u64,
+    pub withdraw: u64,
+    pub remaining: u64,
+    pub receive: u64,
+    pub expiry: i64,
+}
+impl Event for UpdateEvent {
+    const TAG: u8 = 3;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct WithdrawCounterOfferEvent {
+    pub counter: Pubkey,
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    // The taker withdrawing, or the maker rejecting
+    pub authority: Pubkey,
+    pub mint_b: Pubkey,
+    // Token B returned to the taker
+    pub amount: u64,
+}
+impl Event for WithdrawCounterOfferEvent {
+    const TAG: u8 = 4;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowEvent {
+    Make(MakeEvent),
+    Take(TakeEvent),
+    Refund(RefundEvent),
+    Update(UpdateEvent),
+    WithdrawCounterOffer(WithdrawCounterOfferEvent),
+}
+
+// Decode the fields of one `sol_log_data` call
+impl TryFrom<&[&[u8]]> for EscrowEvent {
+    type Error = ProgramError;
+
+    fn try_from(fields: &[&[u8]]) -> Result<Self, Self::Error> {
+        let [[EVENT_VERSION, tag], data] = fields else {
+            return Err(ProgramError::InvalidAccountData);
+        };
+        fn read<T: Pod>(data: &[u8]) -> Result<T, ProgramError> {
+            bytemuck::try_pod_read_unaligned::<T>(data)
+                .map_err(|_| ProgramError::InvalidAccountData)
+        }
+        match *tag {
+            MakeEvent::TAG => Ok(Self::Make(read(data)?)),
+            TakeEvent::TAG => Ok(Self::Take(read(data)?)),
+            RefundEvent::TAG => Ok(Self::Refund(read(data)?)),
+            UpdateEvent::TAG => Ok(Self::Update(read(data)?)),
+            WithdrawCounterOfferEvent::TAG => Ok(Self::WithdrawCounterOffer(read(data)?)),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+#[cfg(not(target_os = "solana"))]
+impl EscrowEvent {
+    // Parse a `Program data: ...` line from a transaction's log messages. The
+    // caller must check from the surrounding invoke logs that it was this program
+    // that logged the line.
+    pub fn from_log(line: &str) -> Option<Self> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let fields = line
+            .strip_prefix("Program data: ")?
+            .split_whitespace()
+            .map(|field| STANDARD.decode(field))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let fields = fields.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Self::try_from(fields.as_slice()).ok()
+    }
+}
@@ -1,20 +1,677 @@
+use shank::ShankInstruction;
 use solana_program::program_error::ProgramError;
 
+use crate::context::{
+    ConfigArgs, Make, MakeBundle, MakeCounterOffer, MakeService, MakeVesting, Migrate,
+    OpenRegistryPage, ResolveService, Take, TakePartial, Update,
+};
+
+// Annotated for shank's IDL. Args are `#[repr(C)]` without implicit padding, so
+// their byte layout is also their borsh encoding.
+// Native SOL legs pass the wallet in place of its token account. Extra accounts
+// for Token-2022 transfer hooks follow the listed ones.
+#[derive(ShankInstruction)]
 pub enum EscrowInstructions {
-    Make,
-    Take,
+    #[account(0, writable, signer, name = "maker", desc = "Maker funding the offer")]
+    #[account(1, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(2, name = "mint_b", desc = "Mint asked in return")]
+    #[account(3, writable, name = "maker_ta_a", desc = "Maker's token A account")]
+    #[account(
+        4,
+        writable,
+        name = "escrow",
+        desc = "Escrow PDA [\"escrow\", maker, seed]"
+    )]
+    #[account(
+        5,
+        writable,
+        name = "vault",
+        desc = "Vault PDA [\"vault\", escrow], created here"
+    )]
+    #[account(6, name = "token_program_a", desc = "Token program of mint A")]
+    #[account(7, name = "token_program_b", desc = "Token program of mint B")]
+    #[account(8, name = "system_program", desc = "System program")]
+    #[account(
+        9,
+        writable,
+        name = "registry",
+        desc = "Registry page to list the offer on, or this program's id"
+    )]
+    Make(Make),
+
+    #[account(0, writable, signer, name = "taker", desc = "Taker filling the offer")]
+    #[account(1, writable, name = "maker", desc = "Maker of the escrow")]
+    #[account(2, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(3, name = "mint_b", desc = "Mint asked in return")]
+    #[account(
+        4,
+        writable,
+        name = "taker_ta_a",
+        desc = "Taker's token A account, created if missing"
+    )]
+    #[account(5, writable, name = "taker_ta_b", desc = "Taker's token B account")]
+    #[account(
+        6,
+        writable,
+        name = "maker_ta_b",
+        desc = "Maker's token B account, created if missing"
+    )]
+    #[account(7, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(8, writable, name = "vault", desc = "Vault PDA")]
+    #[account(9, name = "token_program_a", desc = "Token program of mint A")]
+    #[account(10, name = "token_program_b", desc = "Token program of mint B")]
+    #[account(11, name = "system_program", desc = "System program")]
+    #[account(
+        12,
+        name = "associated_token_program",
+        desc = "Associated token program"
+    )]
+    #[account(
+        13,
+        name = "config",
+        desc = "Fee config PDA [\"config\"], may be uninitialized"
+    )]
+    #[account(
+        14,
+        writable,
+        name = "treasury_ta",
+        desc = "Treasury fee account, unused without a fee"
+    )]
+    #[account(
+        15,
+        writable,
+        name = "registry",
+        desc = "Registry page listing the offer, or this program's id"
+    )]
+    #[account(
+        16,
+        name = "price_feed",
+        desc = "Price feed of an oracle-priced offer, or this program's id"
+    )]
+    Take(Take),
+
+    #[account(
+        0,
+        writable,
+        optional_signer,
+        name = "maker",
+        desc = "Maker, must sign unless the escrow has expired"
+    )]
+    #[account(1, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(2, writable, name = "maker_ta_a", desc = "Maker's token A account")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "vault", desc = "Vault PDA")]
+    #[account(5, name = "token_program", desc = "Token program of mint A")]
+    #[account(6, name = "system_program", desc = "System program")]
+    #[account(
+        7,
+        writable,
+        name = "registry",
+        desc = "Registry page listing the offer, or this program's id"
+    )]
     Refund,
+
+    #[account(0, writable, signer, name = "taker", desc = "Taker filling the offer")]
+    #[account(1, writable, name = "maker", desc = "Maker of the escrow")]
+    #[account(2, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(3, name = "mint_b", desc = "Mint asked in return")]
+    #[account(
+        4,
+        writable,
+        name = "taker_ta_a",
+        desc = "Taker's token A account, created if missing"
+    )]
+    #[account(5, writable, name = "taker_ta_b", desc = "Taker's token B account")]
+    #[account(
+        6,
+        writable,
+        name = "maker_ta_b",
+        desc = "Maker's token B account, created if missing"
+    )]
+    #[account(7, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(8, writable, name = "vault", desc = "Vault PDA")]
+    #[account(9, name = "token_program_a", desc = "Token program of mint A")]
+    #[account(10, name = "token_program_b", desc = "Token program of mint B")]
+    #[account(11, name = "system_program", desc = "System program")]
+    #[account(
+        12,
+        name = "associated_token_program",
+        desc = "Associated token program"
+    )]
+    #[account(
+        13,
+        name = "config",
+        desc = "Fee config PDA [\"config\"], may be uninitialized"
+    )]
+    #[account(
+        14,
+        writable,
+        name = "treasury_ta",
+        desc = "Treasury fee account, unused without a fee"
+    )]
+    #[account(
+        15,
+        writable,
+        name = "registry",
+        desc = "Registry page listing the offer, or this program's id"
+    )]
+    #[account(
+        16,
+        name = "price_feed",
+        desc = "Price feed of an oracle-priced offer, or this program's id"
+    )]
+    TakePartial(TakePartial),
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "admin",
+        desc = "Upgrade authority of the program"
+    )]
+    #[account(1, writable, name = "config", desc = "Fee config PDA [\"config\"]")]
+    #[account(2, name = "treasury", desc = "Wallet receiving fees")]
+    #[account(3, name = "program_data", desc = "ProgramData account of this program")]
+    #[account(4, name = "system_program", desc = "System program")]
+    InitConfig(ConfigArgs),
+
+    #[account(0, signer, name = "admin", desc = "Current config admin")]
+    #[account(1, writable, name = "config", desc = "Fee config PDA")]
+    #[account(2, name = "treasury", desc = "New treasury wallet")]
+    #[account(3, name = "new_admin", desc = "New config admin")]
+    UpdateConfig(ConfigArgs),
+
+    #[account(0, writable, signer, name = "maker", desc = "Maker of the escrow")]
+    #[account(1, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(2, writable, name = "maker_ta_a", desc = "Maker's token A account")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "vault", desc = "Vault PDA")]
+    #[account(5, name = "token_program", desc = "Token program of mint A")]
+    #[account(6, name = "system_program", desc = "System program")]
+    Update(Update),
+
+    #[account(0, writable, signer, name = "payer", desc = "Pays for the page")]
+    #[account(1, name = "mint_a", desc = "Mint offered by listed escrows")]
+    #[account(2, name = "mint_b", desc = "Mint asked by listed escrows")]
+    #[account(
+        3,
+        writable,
+        name = "registry",
+        desc = "Registry page PDA [\"registry\", mint_a, mint_b, page]"
+    )]
+    #[account(4, name = "system_program", desc = "System program")]
+    OpenRegistryPage(OpenRegistryPage),
+
+    // Legs follow in remaining accounts, see `states::bundle`
+    #[account(0, writable, signer, name = "maker", desc = "Maker funding the bundle")]
+    #[account(
+        1,
+        writable,
+        name = "bundle",
+        desc = "Bundle PDA [\"bundle\", maker, seed]"
+    )]
+    #[account(2, name = "system_program", desc = "System program")]
+    MakeBundle(MakeBundle),
+
+    #[account(0, writable, signer, name = "taker", desc = "Taker filling the bundle")]
+    #[account(1, writable, name = "maker", desc = "Maker of the bundle")]
+    #[account(2, writable, name = "bundle", desc = "Bundle PDA")]
+    #[account(
+        3,
+        name = "config",
+        desc = "Fee config PDA [\"config\"], may be uninitialized"
+    )]
+    #[account(4, name = "system_program", desc = "System program")]
+    #[account(
+        5,
+        name = "associated_token_program",
+        desc = "Associated token program"
+    )]
+    TakeBundle,
+
+    #[account(
+        0,
+        writable,
+        optional_signer,
+        name = "maker",
+        desc = "Maker, must sign unless the bundle has expired"
+    )]
+    #[account(1, writable, name = "bundle", desc = "Bundle PDA")]
+    RefundBundle,
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "buyer",
+        desc = "Buyer paying for the service"
+    )]
+    #[account(1, name = "mint", desc = "Mint of the payment")]
+    #[account(2, writable, name = "buyer_ta", desc = "Buyer's token account")]
+    #[account(
+        3,
+        writable,
+        name = "service",
+        desc = "Service PDA [\"service\", buyer, seed]"
+    )]
+    #[account(
+        4,
+        writable,
+        name = "vault",
+        desc = "Vault PDA [\"vault\", service], created here"
+    )]
+    #[account(5, name = "token_program", desc = "Token program of the mint")]
+    #[account(6, name = "system_program", desc = "System program")]
+    MakeService(MakeService),
+
+    #[account(0, writable, signer, name = "buyer", desc = "Buyer approving the work")]
+    #[account(1, writable, name = "seller", desc = "Seller of the service")]
+    #[account(2, name = "mint", desc = "Mint of the deposit")]
+    #[account(3, writable, name = "buyer_ta", desc = "Buyer's token account")]
+    #[account(4, writable, name = "seller_ta", desc = "Seller's token account")]
+    #[account(5, writable, name = "service", desc = "Service PDA")]
+    #[account(6, writable, name = "vault", desc = "Vault PDA")]
+    #[account(7, name = "token_program", desc = "Token program of the mint")]
+    ApproveService,
+
+    #[account(
+        0,
+        signer,
+        name = "party",
+        desc = "Buyer or seller raising the dispute"
+    )]
+    #[account(1, writable, name = "service", desc = "Service PDA")]
+    DisputeService,
+
+    #[account(0, signer, name = "arbiter", desc = "Arbiter named by the buyer")]
+    #[account(1, writable, name = "service", desc = "Service PDA")]
+    ResolveService(ResolveService),
+
+    #[account(0, writable, name = "buyer", desc = "Buyer of the service")]
+    #[account(1, writable, name = "seller", desc = "Seller of the service")]
+    #[account(2, name = "mint", desc = "Mint of the deposit")]
+    #[account(3, writable, name = "buyer_ta", desc = "Buyer's token account")]
+    #[account(4, writable, name = "seller_ta", desc = "Seller's token account")]
+    #[account(5, writable, name = "service", desc = "Service PDA")]
+    #[account(6, writable, name = "vault", desc = "Vault PDA")]
+    #[account(7, name = "token_program", desc = "Token program of the mint")]
+    SettleService,
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "maker",
+        desc = "Maker funding the vesting"
+    )]
+    #[account(1, name = "mint", desc = "Mint being vested")]
+    #[account(2, writable, name = "maker_ta", desc = "Maker's token account")]
+    #[account(
+        3,
+        writable,
+        name = "vesting",
+        desc = "Vesting PDA [\"vesting\", maker, seed]"
+    )]
+    #[account(
+        4,
+        writable,
+        name = "vault",
+        desc = "Vault PDA [\"vault\", vesting], created here"
+    )]
+    #[account(5, name = "token_program", desc = "Token program of the mint")]
+    #[account(6, name = "system_program", desc = "System program")]
+    MakeVesting(MakeVesting),
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "beneficiary",
+        desc = "Beneficiary claiming"
+    )]
+    #[account(
+        1,
+        writable,
+        name = "maker",
+        desc = "Maker, refunded rent after the last claim"
+    )]
+    #[account(2, name = "mint", desc = "Mint being vested")]
+    #[account(
+        3,
+        writable,
+        name = "beneficiary_ta",
+        desc = "Beneficiary's token account"
+    )]
+    #[account(4, writable, name = "vesting", desc = "Vesting PDA")]
+    #[account(5, writable, name = "vault", desc = "Vault PDA")]
+    #[account(6, name = "token_program", desc = "Token program of the mint")]
+    ClaimVesting,
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "maker",
+        desc = "Maker revoking the vesting"
+    )]
+    #[account(1, name = "mint", desc = "Mint being vested")]
+    #[account(2, writable, name = "maker_ta", desc = "Maker's token account")]
+    #[account(3, writable, name = "vesting", desc = "Vesting PDA")]
+    #[account(4, writable, name = "vault", desc = "Vault PDA")]
+    #[account(5, name = "token_program", desc = "Token program of the mint")]
+    RevokeVesting,
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "payer",
+        desc = "Pays the extra rent, and must be the maker of a legacy escrow"
+    )]
+    #[account(1, writable, name = "escrow", desc = "Escrow PDA in an older layout")]
+    #[account(
+        2,
+        name = "vault",
+        desc = "Vault PDA, whose mint and balance become a legacy escrow's token A and remaining amount"
+    )]
+    #[account(3, name = "system_program", desc = "System program")]
+    Migrate(Migrate),
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "taker",
+        desc = "Taker proposing the counter-offer"
+    )]
+    #[account(1, name = "mint_b", desc = "Mint asked by the escrow")]
+    #[account(2, writable, name = "taker_ta_b", desc = "Taker's token B account")]
+    #[account(3, name = "escrow", desc = "Escrow PDA being countered")]
+    #[account(
+        4,
+        writable,
+        name = "counter",
+        desc = "Counter-offer PDA [\"counter\", escrow, taker]"
+    )]
+    #[account(
+        5,
+        writable,
+        name = "vault",
+        desc = "Vault PDA [\"vault\", counter], created here"
+    )]
+    #[account(6, name = "token_program", desc = "Token program of mint B")]
+    #[account(7, name = "system_program", desc = "System program")]
+    MakeCounterOffer(MakeCounterOffer),
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "maker",
+        desc = "Maker accepting the counter-offer"
+    )]
+    #[account(1, writable, name = "taker", desc = "Taker who proposed it")]
+    #[account(2, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(3, name = "mint_b", desc = "Mint asked in return")]
+    #[account(
+        4,
+        writable,
+        name = "maker_ta_b",
+        desc = "Maker's token B account, created if missing"
+    )]
+    #[account(
+        5,
+        writable,
+        name = "taker_ta_a",
+        desc = "Taker's token A account, created if missing"
+    )]
+    #[account(6, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(7, writable, name = "vault", desc = "Vault PDA of the escrow")]
+    #[account(8, writable, name = "counter", desc = "Counter-offer PDA")]
+    #[account(
+        9,
+        writable,
+        name = "counter_vault",
+        desc = "Vault PDA of the counter-offer"
+    )]
+    #[account(10, name = "token_program_a", desc = "Token program of mint A")]
+    #[account(11, name = "token_program_b", desc = "Token program of mint B")]
+    #[account(12, name = "system_program", desc = "System program")]
+    #[account(
+        13,
+        name = "associated_token_program",
+        desc = "Associated token program"
+    )]
+    #[account(
+        14,
+        name = "config",
+        desc = "Fee config PDA [\"config\"], may be uninitialized"
+    )]
+    #[account(
+        15,
+        writable,
+        name = "treasury_ta",
+        desc = "Treasury's account for the fee leg, unused without a fee"
+    )]
+    #[account(
+        16,
+        writable,
+        name = "registry",
+        desc = "Registry page listing the escrow, or this program's id"
+    )]
+    AcceptCounterOffer,
+
+    #[account(
+        0,
+        signer,
+        name = "authority",
+        desc = "Taker withdrawing, or the escrow's maker rejecting"
+    )]
+    #[account(1, writable, name = "taker", desc = "Taker who proposed it")]
+    #[account(2, name = "mint_b", desc = "Mint of the deposit")]
+    #[account(3, writable, name = "taker_ta_b", desc = "Taker's token B account")]
+    #[account(4, name = "escrow", desc = "Escrow the counter-offer was made on")]
+    #[account(5, writable, name = "counter", desc = "Counter-offer PDA")]
+    #[account(6, writable, name = "vault", desc = "Vault PDA of the counter-offer")]
+    #[account(7, name = "token_program", desc = "Token program of mint B")]
+    WithdrawCounterOffer,
+
+    // Escrow X offers mint A for mint B and escrow Y the reverse
+    #[account(
+        0,
+        signer,
+        name = "cranker",
+        desc = "Anyone settling the match, logged as the taker of both escrows"
+    )]
+    #[account(1, name = "config", desc = "Config PDA, which may be uninitialized")]
+    #[account(2, writable, name = "maker_x", desc = "Maker of escrow X")]
+    #[account(3, name = "mint_a", desc = "Mint offered by escrow X")]
+    #[account(4, writable, name = "escrow_x", desc = "Escrow PDA X")]
+    #[account(5, writable, name = "vault_x", desc = "Vault PDA of escrow X")]
+    #[account(6, writable, name = "maker_x_ta_b", desc = "Maker X's token B account")]
+    #[account(
+        7,
+        writable,
+        name = "bounty_ta_a",
+        desc = "Cranker's token A account for a bounty, or this program's id"
+    )]
+    #[account(
+        8,
+        writable,
+        name = "treasury_ta_a",
+        desc = "Treasury's token A account, or the config if no fee is charged"
+    )]
+    #[account(9, name = "token_program_a", desc = "Token program of mint A")]
+    #[account(
+        10,
+        writable,
+        name = "registry_x",
+        desc = "Registry page listing escrow X, or this program's id"
+    )]
+    #[account(11, writable, name = "maker_y", desc = "Maker of escrow Y")]
+    #[account(12, name = "mint_b", desc = "Mint offered by escrow Y")]
+    #[account(13, writable, name = "escrow_y", desc = "Escrow PDA Y")]
+    #[account(14, writable, name = "vault_y", desc = "Vault PDA of escrow Y")]
+    #[account(
+        15,
+        writable,
+        name = "maker_y_ta_a",
+        desc = "Maker Y's token A account"
+    )]
+    #[account(
+        16,
+        writable,
+        name = "bounty_ta_b",
+        desc = "Cranker's token B account for a bounty, or this program's id"
+    )]
+    #[account(
+        17,
+        writable,
+        name = "treasury_ta_b",
+        desc = "Treasury's token B account, or the config if no fee is charged"
+    )]
+    #[account(18, name = "token_program_b", desc = "Token program of mint B")]
+    #[account(
+        19,
+        writable,
+        name = "registry_y",
+        desc = "Registry page listing escrow Y, or this program's id"
+    )]
+    Match,
+
+    #[account(
+        0,
+        writable,
+        signer,
+        name = "relayer",
+        desc = "Relayer filling for the taker, paying for any missing accounts"
+    )]
+    #[account(1, writable, name = "taker", desc = "Taker the offer is filled for")]
+    #[account(2, writable, name = "maker", desc = "Maker of the escrow")]
+    #[account(3, name = "mint_a", desc = "Mint offered by the maker")]
+    #[account(4, name = "mint_b", desc = "Mint asked in return")]
+    #[account(
+        5,
+        writable,
+        name = "taker_ta_a",
+        desc = "Taker's token A account, created if missing"
+    )]
+    #[account(
+        6,
+        writable,
+        name = "taker_ta_b",
+        desc = "Taker's token B account, approving the relayer as delegate"
+    )]
+    #[account(
+        7,
+        writable,
+        name = "maker_ta_b",
+        desc = "Maker's token B account, created if missing"
+    )]
+    #[account(8, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(9, writable, name = "vault", desc = "Vault PDA")]
+    #[account(10, name = "token_program_a", desc = "Token program of mint A")]
+    #[account(11, name = "token_program_b", desc = "Token program of mint B")]
+    #[account(12, name = "system_program", desc = "System program")]
+    #[account(
+        13,
+        name = "associated_token_program",
+        desc = "Associated token program"
+    )]
+    #[account(
+        14,
+        name = "config",
+        desc = "Fee config PDA [\"config\"], may be uninitialized"
+    )]
+    #[account(
+        15,
+        writable,
+        name = "treasury_ta",
+        desc = "Treasury fee account, unused without a fee"
+    )]
+    #[account(
+        16,
+        writable,
+        name = "registry",
+        desc = "Registry page listing the offer, or this program's id"
+    )]
+    #[account(
+        17,
+        name = "price_feed",
+        desc = "Price feed of an oracle-priced offer, or this program's id"
+    )]
+    TakeDelegated(TakePartial),
 }
 
-impl TryFrom<&u8> for EscrowInstructions {
+impl TryFrom<&[u8]> for EscrowInstructions {
     type Error = ProgramError;
 
-    fn try_from(value: &u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Self::Make),
-            1 => Ok(Self::Take),
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let (discriminator, data) = value
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        match discriminator {
+            0 => Ok(Self::Make(Make::try_from(data)?)),
+            1 => Ok(Self::Take(Take::try_from(data)?)),
             2 => Ok(Self::Refund),
+            3 => Ok(Self::TakePartial(TakePartial::try_from(data)?)),
+            4 => Ok(Self::InitConfig(ConfigArgs::try_from(data)?)),
+            5 => Ok(Self::UpdateConfig(ConfigArgs::try_from(data)?)),
+            6 => Ok(Self::Update(Update::try_from(data)?)),
+            7 => Ok(Self::OpenRegistryPage(OpenRegistryPage::try_from(data)?)),
+            8 => Ok(Self::MakeBundle(MakeBundle::try_from(data)?)),
+            9 => Ok(Self::TakeBundle),
+            10 => Ok(Self::RefundBundle),
+            11 => Ok(Self::MakeService(MakeService::try_from(data)?)),
+            12 => Ok(Self::ApproveService),
+            13 => Ok(Self::DisputeService),
+            14 => Ok(Self::ResolveService(ResolveService::try_from(data)?)),
+            15 => Ok(Self::SettleService),
+            16 => Ok(Self::MakeVesting(MakeVesting::try_from(data)?)),
+            17 => Ok(Self::ClaimVesting),
+            18 => Ok(Self::RevokeVesting),
+            19 => Ok(Self::Migrate(Migrate::try_from(data)?)),
+            20 => Ok(Self::MakeCounterOffer(MakeCounterOffer::try_from(data)?)),
+            21 => Ok(Self::AcceptCounterOffer),
+            22 => Ok(Self::WithdrawCounterOffer),
+            23 => Ok(Self::Match),
+            24 => Ok(Self::TakeDelegated(TakePartial::try_from(data)?)),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
 }
+
+impl EscrowInstructions {
+    // Discriminator followed by the args' in-memory layout, as read by `try_from`
+    pub fn pack(&self) -> Vec<u8> {
+        let (discriminator, args): (u8, &[u8]) = match self {
+            Self::Make(args) => (0, bytemuck::bytes_of(args)),
+            Self::Take(args) => (1, bytemuck::bytes_of(args)),
+            Self::Refund => (2, &[]),
+            Self::TakePartial(args) => (3, bytemuck::bytes_of(args)),
+            Self::InitConfig(args) => (4, bytemuck::bytes_of(args)),
+            Self::UpdateConfig(args) => (5, bytemuck::bytes_of(args)),
+            Self::Update(args) => (6, bytemuck::bytes_of(args)),
+            Self::OpenRegistryPage(args) => (7, bytemuck::bytes_of(args)),
+            Self::MakeBundle(args) => (8, bytemuck::bytes_of(args)),
+            Self::TakeBundle => (9, &[]),
+            Self::RefundBundle => (10, &[]),
+            Self::MakeService(args) => (11, bytemuck::bytes_of(args)),
+            Self::ApproveService => (12, &[]),
+            Self::DisputeService => (13, &[]),
+            Self::ResolveService(args) => (14, bytemuck::bytes_of(args)),
+            Self::SettleService => (15, &[]),
+            Self::MakeVesting(args) => (16, bytemuck::bytes_of(args)),
+            Self::ClaimVesting => (17, &[]),
+            Self::RevokeVesting => (18, &[]),
+            Self::Migrate(args) => (19, bytemuck::bytes_of(args)),
+            Self::MakeCounterOffer(args) => (20, bytemuck::bytes_of(args)),
+            Self::AcceptCounterOffer => (21, &[]),
+            Self::WithdrawCounterOffer => (22, &[]),
+            Self::Match => (23, &[]),
+            Self::TakeDelegated(args) => (24, bytemuck::bytes_of(args)),
+        };
+        [&[discriminator], args].concat()
+    }
+}
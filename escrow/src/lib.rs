@@ -1,20 +1,35 @@
+// State handlers take their accounts one by one rather than as a struct
+#![allow(clippy::too_many_arguments)]
+#[cfg(not(target_os = "solana"))]
+pub mod client;
 pub mod context;
-mod instructions;
+pub mod error;
+pub mod events;
+pub mod instructions;
+#[cfg(feature = "optimized")]
+pub mod optimized;
 pub mod states;
 use solana_program::{
-    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, program_error::ProgramError,
-    pubkey, pubkey::Pubkey,
+    account_info::AccountInfo, declare_id, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
 };
 #[cfg(test)]
 mod tests;
 use crate::{
-    context::{make, refund, take},
+    context::{
+        accept_counter_offer, approve_service, claim_vesting, dispute_service, init_config, make,
+        make_bundle, make_counter_offer, make_service, make_vesting, match_escrows, migrate,
+        open_registry_page, refund, refund_bundle, resolve_service, revoke_vesting, settle_service,
+        take, take_bundle, update, update_config, withdraw_counter_offer,
+    },
     instructions::EscrowInstructions,
 };
 
-const ID: Pubkey = pubkey!("GYR4e4wWTg9KttwwjEsCmRPUsjxPzjEZ5BrhVFYm7KMW");
+declare_id!("GYR4e4wWTg9KttwwjEsCmRPUsjxPzjEZ5BrhVFYm7KMW");
 
-entrypoint!(process_instruction);
+// The optimized build brings its own entrypoint
+#[cfg(not(any(feature = "no-entrypoint", feature = "optimized")))]
+solana_program::entrypoint!(process_instruction);
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -25,13 +40,31 @@ pub fn process_instruction(
         return Err(ProgramError::IncorrectProgramId);
     }
 
-    let (discriminator, data) = data
-        .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
-
-    match EscrowInstructions::try_from(discriminator)? {
-        EscrowInstructions::Make => make::process(accounts, data),
-        EscrowInstructions::Take => take::process(accounts),
+    match EscrowInstructions::try_from(data)? {
+        EscrowInstructions::Make(args) => make::process(accounts, args),
+        EscrowInstructions::Take(args) => take::process(accounts, args),
         EscrowInstructions::Refund => refund::process(accounts),
+        EscrowInstructions::TakePartial(args) => take::process_partial(accounts, args),
+        EscrowInstructions::InitConfig(args) => init_config::process(accounts, args),
+        EscrowInstructions::UpdateConfig(args) => update_config::process(accounts, args),
+        EscrowInstructions::Update(args) => update::process(accounts, args),
+        EscrowInstructions::OpenRegistryPage(args) => open_registry_page::process(accounts, args),
+        EscrowInstructions::MakeBundle(args) => make_bundle::process(accounts, args),
+        EscrowInstructions::TakeBundle => take_bundle::process(accounts),
+        EscrowInstructions::RefundBundle => refund_bundle::process(accounts),
+        EscrowInstructions::MakeService(args) => make_service::process(accounts, args),
+        EscrowInstructions::ApproveService => approve_service::process(accounts),
+        EscrowInstructions::DisputeService => dispute_service::process(accounts),
+        EscrowInstructions::ResolveService(args) => resolve_service::process(accounts, args),
+        EscrowInstructions::SettleService => settle_service::process(accounts),
+        EscrowInstructions::MakeVesting(args) => make_vesting::process(accounts, args),
+        EscrowInstructions::ClaimVesting => claim_vesting::process(accounts),
+        EscrowInstructions::RevokeVesting => revoke_vesting::process(accounts),
+        EscrowInstructions::Migrate(args) => migrate::process(accounts, args),
+        EscrowInstructions::MakeCounterOffer(args) => make_counter_offer::process(accounts, args),
+        EscrowInstructions::AcceptCounterOffer => accept_counter_offer::process(accounts),
+        EscrowInstructions::WithdrawCounterOffer => withdraw_counter_offer::process(accounts),
+        EscrowInstructions::Match => match_escrows::process(accounts),
+        EscrowInstructions::TakeDelegated(args) => take::process_delegated(accounts, args),
     }
 }
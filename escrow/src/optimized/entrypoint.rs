@@ -0,0 +1,64 @@
+// Runs the fast path when it takes the instruction, otherwise hands the input to
+// the native processor
+use core::mem::{size_of, MaybeUninit};
+
+use pinocchio::{
+    account_info::AccountInfo, entrypoint::deserialize, pubkey::Pubkey, MAX_TX_ACCOUNTS, SUCCESS,
+};
+use solana_program::entrypoint::{BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER};
+
+use super::FastPath;
+
+pinocchio::default_allocator!();
+pinocchio::default_panic_handler!();
+
+/// # Safety
+///
+/// `input` must be the parameter buffer serialized by the runtime.
+#[no_mangle]
+pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
+    const UNINIT: MaybeUninit<AccountInfo> = MaybeUninit::uninit();
+    let mut accounts = [UNINIT; MAX_TX_ACCOUNTS];
+    let (program_id, count, data) = deserialize::<MAX_TX_ACCOUNTS>(input, &mut accounts);
+    let accounts = core::slice::from_raw_parts(accounts.as_ptr() as *const AccountInfo, count);
+
+    if let Some(mut instruction) = FastPath::new(program_id, accounts, data) {
+        return match instruction.process() {
+            Ok(()) => SUCCESS,
+            Err(error) => error.into(),
+        };
+    }
+
+    // Nothing has been written yet, so the native processor sees the input as sent
+    reset_borrow_state(input, accounts);
+    let (program_id, accounts, data) = solana_program::entrypoint::deserialize(input);
+    match crate::process_instruction(program_id, &accounts, data) {
+        Ok(()) => SUCCESS,
+        Err(error) => error.into(),
+    }
+}
+
+// Pinocchio reuses each account's duplicate marker as its borrow flag. Put the
+// markers back, walking the input the way the runtime laid it out.
+unsafe fn reset_borrow_state(input: *mut u8, accounts: &[AccountInfo]) {
+    // Marker, flags and original length, then key, owner, lamports and data length
+    const KEY: usize = 8;
+    const DATA_LEN: usize = KEY + 32 + 32 + 8;
+    const HEADER: usize = DATA_LEN + 8;
+
+    let mut offset = size_of::<u64>();
+    for account in accounts {
+        let record = input.add(offset);
+        // A duplicate is a bare index pointing back at an earlier record
+        if !core::ptr::eq(account.key(), record.add(KEY) as *const Pubkey) {
+            offset += size_of::<u64>();
+            continue;
+        }
+        *record = NON_DUP_MARKER;
+        let data_len = *(record.add(DATA_LEN) as *const u64) as usize;
+        offset += HEADER + data_len + MAX_PERMITTED_DATA_INCREASE;
+        offset = offset.next_multiple_of(BPF_ALIGN_OF_U128);
+        // Rent epoch
+        offset += size_of::<u64>();
+    }
+}
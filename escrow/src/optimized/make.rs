@@ -0,0 +1,208 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::find_program_address,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{
+    instructions::{InitializeAccount3, TransferChecked},
+    state::{Mint, TokenAccount},
+};
+use solana_program::pubkey::Pubkey as SolanaPubkey;
+
+use super::{check_token_program, ID};
+use crate::{
+    context::Make as MakeInstructionData,
+    error::EscrowError,
+    events::{Event, MakeEvent},
+    states::{Escrow, ESCROW_DISCRIMINATOR, ESCROW_VERSION},
+};
+
+pub struct MakeAccounts<'a> {
+    pub maker: &'a AccountInfo,
+    pub mint_a: &'a AccountInfo,
+    pub mint_b: &'a AccountInfo,
+    pub maker_ta_a: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub vault: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [maker, mint_a, mint_b, maker_ta_a, escrow, vault, token_program_a, token_program_b, _system_program, registry, ..] =
+            accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !maker.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        check_token_program(token_program_a, mint_a)?;
+        check_token_program(token_program_b, mint_b)?;
+        // Offers can't be listed on a registry page
+        if registry.key() != &ID {
+            return Err(EscrowError::Unsupported.into());
+        }
+
+        Ok(Self {
+            maker,
+            mint_a,
+            mint_b,
+            maker_ta_a,
+            escrow,
+            vault,
+        })
+    }
+}
+
+pub struct Make<'a> {
+    pub accounts: MakeAccounts<'a>,
+    pub instruction_datas: MakeInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Make<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = MakeAccounts::try_from(accounts)?;
+        let instruction_datas = bytemuck::try_pod_read_unaligned::<MakeInstructionData>(data)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        // Oracle-priced asks need the native build
+        if !instruction_datas.pricing.is_fixed() {
+            return Err(EscrowError::Unsupported.into());
+        }
+        // Same rule as the native build, a fixed ask can't be nothing
+        if instruction_datas.receive == 0 {
+            return Err(EscrowError::InvalidReceive.into());
+        }
+
+        Ok(Self {
+            accounts,
+            instruction_datas,
+        })
+    }
+}
+
+impl<'a> Make<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &0;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let MakeAccounts {
+            maker,
+            mint_a,
+            mint_b,
+            maker_ta_a,
+            escrow,
+            vault,
+        } = self.accounts;
+        let MakeInstructionData {
+            seed,
+            amount,
+            receive,
+            expiry,
+            taker,
+            pricing,
+        } = self.instruction_datas;
+
+        // The only searches for bumps; Take and Refund read them back from the escrow
+        let seed_bytes = seed.to_le_bytes();
+        let (escrow_key, bump) = find_program_address(&[b"escrow", maker.key(), &seed_bytes], &ID);
+        if escrow.key() != &escrow_key {
+            return Err(EscrowError::InvalidPda.into());
+        }
+        let (vault_key, vault_bump) = find_program_address(&[b"vault", escrow.key()], &ID);
+        if vault.key() != &vault_key {
+            return Err(EscrowError::InvalidPda.into());
+        }
+
+        let rent = Rent::get()?;
+        let bump = [bump];
+        let escrow_seeds = [
+            Seed::from(b"escrow"),
+            Seed::from(maker.key()),
+            Seed::from(&seed_bytes),
+            Seed::from(&bump),
+        ];
+        CreateAccount {
+            from: maker,
+            to: escrow,
+            lamports: rent.minimum_balance(core::mem::size_of::<Escrow>()),
+            space: core::mem::size_of::<Escrow>() as u64,
+            owner: &ID,
+        }
+        .invoke_signed(&[Signer::from(&escrow_seeds)])?;
+
+        let vault_bump = [vault_bump];
+        let vault_seeds = [
+            Seed::from(b"vault"),
+            Seed::from(escrow.key()),
+            Seed::from(&vault_bump),
+        ];
+        CreateAccount {
+            from: maker,
+            to: vault,
+            lamports: rent.minimum_balance(TokenAccount::LEN),
+            space: TokenAccount::LEN as u64,
+            owner: &pinocchio_token::ID,
+        }
+        .invoke_signed(&[Signer::from(&vault_seeds)])?;
+        InitializeAccount3 {
+            account: vault,
+            mint: mint_a,
+            owner: escrow.key(),
+        }
+        .invoke()?;
+
+        // Classic SPL Token charges no transfer fee, so the whole amount lands
+        TransferChecked {
+            from: maker_ta_a,
+            mint: mint_a,
+            to: vault,
+            authority: maker,
+            amount,
+            decimals: Mint::from_account_info(mint_a)?.decimals(),
+        }
+        .invoke()?;
+
+        let mut data = escrow.try_borrow_mut_data()?;
+        let escrow_data = bytemuck::try_from_bytes_mut::<Escrow>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        *escrow_data = Escrow {
+            discriminator: ESCROW_DISCRIMINATOR,
+            version: ESCROW_VERSION,
+            bump: bump[0],
+            vault_bump: vault_bump[0],
+            _padding: [0; 4],
+            seed,
+            maker: SolanaPubkey::new_from_array(*maker.key()),
+            mint_a: SolanaPubkey::new_from_array(*mint_a.key()),
+            mint_b: SolanaPubkey::new_from_array(*mint_b.key()),
+            receive,
+            remaining: amount,
+            expiry,
+            taker,
+            registry: SolanaPubkey::default(),
+            pricing,
+        };
+
+        MakeEvent {
+            escrow: SolanaPubkey::new_from_array(*escrow.key()),
+            maker: escrow_data.maker,
+            mint_a: escrow_data.mint_a,
+            mint_b: escrow_data.mint_b,
+            taker,
+            amount,
+            receive,
+            expiry,
+        }
+        .emit();
+        Ok(())
+    }
+}
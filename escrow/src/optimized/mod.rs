@@ -0,0 +1,159 @@
+// Pinocchio build of the hot path, with the same instruction ABI as the native
+// build. Make, Take, TakePartial and Refund of fixed-price, unlisted offers
+// between classic SPL Token mints read bumps from the escrow instead of searching
+// for them and never allocate. Every other instruction, and any of these that
+// uses native SOL, Token-2022, a registry page or a price feed, is handed to the
+// native processor, so the build is a drop-in replacement. That fallback links
+// solana-program, so unlike vault1 this crate isn't no_std.
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    ProgramResult,
+};
+
+use crate::{
+    context::{Take as TakeInstructionData, TakePartial},
+    error::EscrowError,
+    states::{Escrow, LegacyEscrow, ESCROW_DISCRIMINATOR, ESCROW_VERSION},
+};
+
+#[cfg(not(feature = "no-entrypoint"))]
+pub mod entrypoint;
+pub mod make;
+pub mod refund;
+pub mod take;
+
+pub use make::*;
+pub use refund::*;
+pub use take::*;
+
+pub const ID: Pubkey = five8_const::decode_32_const("GYR4e4wWTg9KttwwjEsCmRPUsjxPzjEZ5BrhVFYm7KMW");
+
+// ["config"] under ID, so fills needn't search for it
+pub const CONFIG: Pubkey =
+    five8_const::decode_32_const("E7kHD9Tq3uf8drsMWSPHrFkiByik8Vg1nBRwADWLj2cm");
+
+pub const NATIVE_MINT: Pubkey =
+    five8_const::decode_32_const("So11111111111111111111111111111111111111112");
+
+// An instruction the fast path has checked and will settle
+pub enum FastPath<'a> {
+    Make(Make<'a>),
+    Take(Take<'a>),
+    Refund(Refund<'a>),
+}
+
+impl<'a> FastPath<'a> {
+    // None for anything outside the fast path or failing its checks, which the
+    // native processor then settles or rejects with its own error
+    pub fn new(program_id: &Pubkey, accounts: &'a [AccountInfo], data: &'a [u8]) -> Option<Self> {
+        if program_id != &ID {
+            return None;
+        }
+
+        match data.split_first()? {
+            (Make::DISCRIMINATOR, data) => Make::try_from((data, accounts)).ok().map(Self::Make),
+            (Take::DISCRIMINATOR, data) => {
+                let limits = TakeInstructionData::try_from(data).ok()?;
+                Take::try_from((None, limits, accounts))
+                    .ok()
+                    .map(Self::Take)
+            }
+            (Take::PARTIAL_DISCRIMINATOR, data) => {
+                let args = TakePartial::try_from(data).ok()?;
+                Take::try_from((Some(args.amount), args.limits(), accounts))
+                    .ok()
+                    .map(Self::Take)
+            }
+            (Refund::DISCRIMINATOR, _) => Refund::try_from(accounts).ok().map(Self::Refund),
+            _ => None,
+        }
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        match self {
+            Self::Make(make) => make.process(),
+            Self::Take(take) => take.process(),
+            Self::Refund(refund) => refund.process(),
+        }
+    }
+}
+
+// Errors from the shared state helpers carry the same codes
+#[inline(always)]
+fn native_error(e: solana_program::program_error::ProgramError) -> ProgramError {
+    u64::from(e).into()
+}
+
+#[inline(always)]
+fn check_pda(seeds: &[&[u8]], account: &AccountInfo) -> ProgramResult {
+    let address = create_program_address(seeds, &ID).map_err(|_| EscrowError::InvalidPda)?;
+    if &address != account.key() {
+        return Err(EscrowError::InvalidPda.into());
+    }
+    Ok(())
+}
+
+// Each leg must be a classic SPL Token mint other than the native mint, which
+// the native build settles in lamports
+#[inline(always)]
+fn check_token_program(token_program: &AccountInfo, mint: &AccountInfo) -> ProgramResult {
+    if mint.key() == &NATIVE_MINT {
+        return Err(EscrowError::Unsupported.into());
+    }
+    if token_program.key() != &pinocchio_token::ID {
+        return Err(EscrowError::UnsupportedTokenProgram.into());
+    }
+    if unsafe { mint.owner() } != &pinocchio_token::ID {
+        return Err(EscrowError::TokenProgramMismatch.into());
+    }
+    Ok(())
+}
+
+// Read the maker's escrow, checking its address against the stored bump
+#[inline(always)]
+fn load_escrow(maker: &AccountInfo, escrow: &AccountInfo) -> Result<Escrow, ProgramError> {
+    if unsafe { escrow.owner() } != &ID {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if escrow.data_len() == core::mem::size_of::<LegacyEscrow>() {
+        return Err(EscrowError::OutdatedAccount.into());
+    }
+    let escrow_data = *bytemuck::try_from_bytes::<Escrow>(&escrow.try_borrow_data()?)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if escrow_data.discriminator != ESCROW_DISCRIMINATOR {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if escrow_data.version != ESCROW_VERSION {
+        return Err(EscrowError::OutdatedAccount.into());
+    }
+    // Registry pages and price feeds are only handled by the native build
+    if escrow_data.registry != solana_program::pubkey::Pubkey::default()
+        || !escrow_data.pricing.is_fixed()
+    {
+        return Err(EscrowError::Unsupported.into());
+    }
+
+    check_pda(
+        &[
+            b"escrow",
+            maker.key(),
+            &escrow_data.seed.to_le_bytes(),
+            &[escrow_data.bump],
+        ],
+        escrow,
+    )?;
+    Ok(escrow_data)
+}
+
+// Close the escrow once its vault is closed, returning its rent to the maker
+#[inline(always)]
+fn close_escrow(escrow: &AccountInfo, maker: &AccountInfo) -> ProgramResult {
+    let balance = escrow.lamports();
+    escrow.realloc(0, false)?;
+    *escrow.try_borrow_mut_lamports()? = 0;
+    *maker.try_borrow_mut_lamports()? += balance;
+    unsafe { escrow.assign(&pinocchio_system::ID) };
+    Ok(())
+}
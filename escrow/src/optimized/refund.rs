@@ -0,0 +1,133 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+use pinocchio_token::{
+    instructions::{CloseAccount, TransferChecked},
+    state::{Mint, TokenAccount},
+};
+use solana_program::pubkey::Pubkey as SolanaPubkey;
+
+use super::{check_pda, check_token_program, close_escrow, load_escrow};
+use crate::{
+    error::EscrowError,
+    events::{Event, RefundEvent},
+    states::Escrow,
+};
+
+pub struct RefundAccounts<'a> {
+    pub maker: &'a AccountInfo,
+    pub mint_a: &'a AccountInfo,
+    pub maker_ta_a: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub vault: &'a AccountInfo,
+    pub escrow_data: Escrow,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for RefundAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [maker, mint_a, maker_ta_a, escrow, vault, token_program, _system_program, _registry, ..] =
+            accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program, mint_a)?;
+        let escrow_data = load_escrow(maker, escrow)?;
+
+        // Only the maker can refund a live offer; once expired anyone can crank it
+        if !maker.is_signer() && !escrow_data.is_expired(Clock::get()?.unix_timestamp) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        check_pda(&[b"vault", escrow.key(), &[escrow_data.vault_bump]], vault)?;
+        if mint_a.key() != &escrow_data.mint_a.to_bytes() {
+            return Err(EscrowError::MintMismatch.into());
+        }
+        // Refunds can be cranked by anyone, so token A must go back to the maker
+        if TokenAccount::from_account_info(maker_ta_a)?.owner() != maker.key() {
+            return Err(EscrowError::InvalidMakerTokenAccount.into());
+        }
+
+        Ok(Self {
+            maker,
+            mint_a,
+            maker_ta_a,
+            escrow,
+            vault,
+            escrow_data,
+        })
+    }
+}
+
+pub struct Refund<'a> {
+    pub accounts: RefundAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for Refund<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let accounts = RefundAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> Refund<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &2;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let RefundAccounts {
+            maker,
+            mint_a,
+            maker_ta_a,
+            escrow,
+            vault,
+            escrow_data,
+        } = self.accounts;
+
+        let seed = escrow_data.seed.to_le_bytes();
+        let bump = [escrow_data.bump];
+        let seeds = [
+            Seed::from(b"escrow"),
+            Seed::from(maker.key()),
+            Seed::from(&seed),
+            Seed::from(&bump),
+        ];
+        let signers = [Signer::from(&seeds)];
+
+        // Return token A and rent to maker, close the vault & escrow
+        let amount = TokenAccount::from_account_info(vault)?.amount();
+        TransferChecked {
+            from: vault,
+            mint: mint_a,
+            to: maker_ta_a,
+            authority: escrow,
+            amount,
+            decimals: Mint::from_account_info(mint_a)?.decimals(),
+        }
+        .invoke_signed(&signers)?;
+
+        RefundEvent {
+            escrow: SolanaPubkey::new_from_array(*escrow.key()),
+            maker: escrow_data.maker,
+            mint_a: escrow_data.mint_a,
+            amount,
+        }
+        .emit();
+
+        CloseAccount {
+            account: vault,
+            destination: maker,
+            authority: escrow,
+        }
+        .invoke_signed(&signers)?;
+        close_escrow(escrow, maker)
+    }
+}
@@ -0,0 +1,302 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+use pinocchio_associated_token_account::instructions::CreateIdempotent;
+use pinocchio_token::{
+    instructions::{CloseAccount, TransferChecked},
+    state::{Mint, TokenAccount},
+};
+use solana_program::pubkey::Pubkey as SolanaPubkey;
+
+use super::{check_pda, check_token_program, close_escrow, load_escrow, native_error, CONFIG, ID};
+use crate::{
+    context::Take as TakeInstructionData,
+    error::EscrowError,
+    events::{Event, TakeEvent},
+    states::{Config, Escrow, FEE_LEG_A},
+};
+
+pub struct TakeAccounts<'a> {
+    pub taker: &'a AccountInfo,
+    pub maker: &'a AccountInfo,
+    pub mint_a: &'a AccountInfo,
+    pub mint_b: &'a AccountInfo,
+    pub taker_ta_a: &'a AccountInfo,
+    pub taker_ta_b: &'a AccountInfo,
+    pub maker_ta_b: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub vault: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub treasury_ta: &'a AccountInfo,
+    pub escrow_data: Escrow,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for TakeAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [taker, maker, mint_a, mint_b, taker_ta_a, taker_ta_b, maker_ta_b, escrow, vault, token_program_a, token_program_b, system_program, _associated_token_program, config, treasury_ta, _registry, _price_feed, ..] =
+            accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program_a, mint_a)?;
+        check_token_program(token_program_b, mint_b)?;
+        let escrow_data = load_escrow(maker, escrow)?;
+        check_pda(&[b"vault", escrow.key(), &[escrow_data.vault_bump]], vault)?;
+        if mint_a.key() != &escrow_data.mint_a.to_bytes()
+            || mint_b.key() != &escrow_data.mint_b.to_bytes()
+        {
+            return Err(EscrowError::MintMismatch.into());
+        }
+
+        // Private offers can only be filled by the designated taker
+        if !taker.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if escrow_data.taker != SolanaPubkey::default()
+            && escrow_data.taker.to_bytes() != *taker.key()
+        {
+            return Err(EscrowError::InvalidTaker.into());
+        }
+        if config.key() != &CONFIG {
+            return Err(EscrowError::InvalidPda.into());
+        }
+
+        Ok(Self {
+            taker,
+            maker,
+            mint_a,
+            mint_b,
+            taker_ta_a,
+            taker_ta_b,
+            maker_ta_b,
+            escrow,
+            vault,
+            token_program: token_program_a,
+            system_program,
+            config,
+            treasury_ta,
+            escrow_data,
+        })
+    }
+}
+
+pub struct Take<'a> {
+    pub accounts: TakeAccounts<'a>,
+    // Token A to fill, or everything left for a plain Take
+    pub amount: u64,
+    pub instruction_datas: TakeInstructionData,
+}
+
+impl<'a> TryFrom<(Option<u64>, TakeInstructionData, &'a [AccountInfo])> for Take<'a> {
+    type Error = ProgramError;
+
+    fn try_from(
+        (amount, instruction_datas, accounts): (
+            Option<u64>,
+            TakeInstructionData,
+            &'a [AccountInfo],
+        ),
+    ) -> Result<Self, Self::Error> {
+        let accounts = TakeAccounts::try_from(accounts)?;
+        let amount = amount.unwrap_or(accounts.escrow_data.remaining);
+
+        Ok(Self {
+            accounts,
+            amount,
+            instruction_datas,
+        })
+    }
+}
+
+impl<'a> Take<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &1;
+    pub const PARTIAL_DISCRIMINATOR: &'a u8 = &3;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let TakeAccounts {
+            taker,
+            maker,
+            mint_a,
+            mint_b,
+            taker_ta_a,
+            taker_ta_b,
+            maker_ta_b,
+            escrow,
+            vault,
+            token_program,
+            system_program,
+            config,
+            treasury_ta,
+            escrow_data,
+        } = self.accounts;
+
+        if escrow_data.is_expired(Clock::get()?.unix_timestamp) {
+            return Err(EscrowError::Expired.into());
+        }
+
+        // Create the taker's token A and maker's token B accounts if missing, paid for by the taker
+        for (ata, wallet, mint) in [(taker_ta_a, taker, mint_a), (maker_ta_b, maker, mint_b)] {
+            if ata.data_is_empty() {
+                CreateIdempotent {
+                    funding_account: taker,
+                    account: ata,
+                    wallet,
+                    mint,
+                    system_program,
+                    token_program,
+                }
+                .invoke()?;
+            }
+        }
+        if TokenAccount::from_account_info(maker_ta_b)?.owner() != maker.key() {
+            return Err(EscrowError::InvalidMakerTokenAccount.into());
+        }
+
+        let cost = escrow_data.fill_cost(self.amount).map_err(native_error)?;
+        let filled = self.amount == escrow_data.remaining;
+        // The last fill sweeps the whole vault so it can be closed
+        let amount = if filled {
+            TokenAccount::from_account_info(vault)?.amount()
+        } else {
+            self.amount
+        };
+
+        // Protocol fee, charged to the taker on the configured leg
+        let config_data = if config.data_is_empty() {
+            None
+        } else {
+            if unsafe { config.owner() } != &ID {
+                return Err(ProgramError::IllegalOwner);
+            }
+            Some(
+                *bytemuck::try_from_bytes::<Config>(&config.try_borrow_data()?)
+                    .map_err(|_| ProgramError::InvalidAccountData)?,
+            )
+        };
+        let (fee_a, fee_b) = match config_data {
+            Some(config_data) if config_data.fee_leg == FEE_LEG_A => (config_data.fee(amount), 0),
+            Some(config_data) => (0, config_data.fee(cost)),
+            None => (0, 0),
+        };
+        if let Some(config_data) = config_data.filter(|_| fee_a + fee_b > 0) {
+            if TokenAccount::from_account_info(treasury_ta)?.owner()
+                != &config_data.treasury.to_bytes()
+            {
+                return Err(EscrowError::InvalidTreasury.into());
+            }
+        }
+
+        // Hold the maker to the terms the taker signed for. Classic SPL Token
+        // withholds no transfer fee, so the taker receives all of `amount - fee_a`.
+        let TakeInstructionData {
+            min_amount,
+            max_payment,
+        } = self.instruction_datas;
+        if amount - fee_a < min_amount || cost.saturating_add(fee_b) > max_payment {
+            return Err(EscrowError::SlippageExceeded.into());
+        }
+
+        let seed = escrow_data.seed.to_le_bytes();
+        let bump = [escrow_data.bump];
+        let seeds = [
+            Seed::from(b"escrow"),
+            Seed::from(maker.key()),
+            Seed::from(&seed),
+            Seed::from(&bump),
+        ];
+        let signers = [Signer::from(&seeds)];
+        let decimals_a = Mint::from_account_info(mint_a)?.decimals();
+        let decimals_b = Mint::from_account_info(mint_b)?.decimals();
+
+        //claim token A to taker
+        TransferChecked {
+            from: vault,
+            mint: mint_a,
+            to: taker_ta_a,
+            authority: escrow,
+            amount: amount - fee_a,
+            decimals: decimals_a,
+        }
+        .invoke_signed(&signers)?;
+
+        //transfer token B to maker, which classic SPL Token delivers in full
+        TransferChecked {
+            from: taker_ta_b,
+            mint: mint_b,
+            to: maker_ta_b,
+            authority: taker,
+            amount: cost,
+            decimals: decimals_b,
+        }
+        .invoke()?;
+
+        //collect the protocol fee
+        if fee_a > 0 {
+            TransferChecked {
+                from: vault,
+                mint: mint_a,
+                to: treasury_ta,
+                authority: escrow,
+                amount: fee_a,
+                decimals: decimals_a,
+            }
+            .invoke_signed(&signers)?;
+        }
+        if fee_b > 0 {
+            TransferChecked {
+                from: taker_ta_b,
+                mint: mint_b,
+                to: treasury_ta,
+                authority: taker,
+                amount: fee_b,
+                decimals: decimals_b,
+            }
+            .invoke()?;
+        }
+
+        TakeEvent {
+            escrow: SolanaPubkey::new_from_array(*escrow.key()),
+            maker: escrow_data.maker,
+            taker: SolanaPubkey::new_from_array(*taker.key()),
+            mint_a: escrow_data.mint_a,
+            mint_b: escrow_data.mint_b,
+            amount,
+            paid: cost,
+            fee_a,
+            fee_b,
+            remaining: if filled {
+                0
+            } else {
+                escrow_data.remaining - amount
+            },
+        }
+        .emit();
+
+        // Partial fill: keep the escrow open with what is left
+        if !filled {
+            let mut data = escrow.try_borrow_mut_data()?;
+            let escrow_state = bytemuck::try_from_bytes_mut::<Escrow>(&mut data)
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+            escrow_state.remaining -= amount;
+            escrow_state.receive -= cost;
+            return Ok(());
+        }
+
+        CloseAccount {
+            account: vault,
+            destination: maker,
+            authority: escrow,
+        }
+        .invoke_signed(&signers)?;
+        close_escrow(escrow, maker)
+    }
+}
@@ -0,0 +1,416 @@
+use super::config::{Config, FEE_LEG_A};
+use super::utils::{
+    add_transfer_fee, check_eq_pda, check_eq_pda_and_get_bump, check_token_program,
+    close_token_account, create_token_account, get_amount, get_owner, init_ata_if_needed,
+    is_native, transfer,
+};
+use crate::error::EscrowError;
+use bytemuck::{Pod, Zeroable};
+use shank::{ShankAccount, ShankType};
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction::create_account,
+    sysvar::Sysvar,
+};
+
+pub const MAX_BUNDLE_LEGS: usize = 4;
+
+// Shank only reads literal array lengths, so the legs spell it out
+const _: () = assert!(MAX_BUNDLE_LEGS == 4);
+
+// Remaining accounts taken by each leg, in order:
+// Make offer: mint, maker token account, vault, token program
+// Make ask: mint, token program
+// Take offer: mint, vault, taker token account, treasury token account, token program
+// Take ask: mint, taker token account, maker token account, treasury token account, token program
+// Refund offer: mint, maker token account, vault, token program
+// Accounts for transfer hooks follow the legs.
+pub const MAKE_OFFER_ACCOUNTS: usize = 4;
+pub const MAKE_ASK_ACCOUNTS: usize = 2;
+pub const TAKE_OFFER_ACCOUNTS: usize = 5;
+pub const TAKE_ASK_ACCOUNTS: usize = 5;
+pub const REFUND_OFFER_ACCOUNTS: usize = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankType)]
+pub struct Leg {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+// Several tokens offered for several tokens, settled all at once. Each offered
+// mint sits in its own vault at ["bundle_vault", bundle, mint].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankAccount)]
+pub struct Bundle {
+    pub seed: u64,
+    pub maker: Pubkey,
+    // Only this counterparty can fill the offer, default if anyone can
+    pub taker: Pubkey,
+    // Unix timestamp after which the offer can no longer be taken, 0 if it never expires
+    pub expiry: i64,
+    pub offer_count: u8,
+    pub ask_count: u8,
+    #[padding]
+    pub _padding: [u8; 6],
+    pub offers: [Leg; 4],
+    pub asks: [Leg; 4],
+}
+
+impl Bundle {
+    #[inline]
+    pub fn get_data_and_bump(
+        maker: &Pubkey,
+        bundle: &AccountInfo,
+    ) -> Result<(Bundle, u8), ProgramError> {
+        if bundle.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let bundle_data = *bytemuck::try_from_bytes::<Bundle>(*bundle.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+
+        let bump = check_eq_pda_and_get_bump(
+            &[
+                b"bundle",
+                maker.as_ref(),
+                bundle_data.seed.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+            bundle.key,
+        )?;
+        Ok((bundle_data, bump))
+    }
+
+    #[inline]
+    pub fn offers(&self) -> &[Leg] {
+        &self.offers[..self.offer_count as usize]
+    }
+
+    #[inline]
+    pub fn asks(&self) -> &[Leg] {
+        &self.asks[..self.ask_count as usize]
+    }
+
+    #[inline]
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry != 0 && now >= self.expiry
+    }
+
+    // Each side needs 1 to MAX_BUNDLE_LEGS legs
+    #[inline]
+    pub fn check_leg_counts(offer_count: u8, ask_count: u8) -> ProgramResult {
+        if !(1..=MAX_BUNDLE_LEGS).contains(&(offer_count as usize))
+            || !(1..=MAX_BUNDLE_LEGS).contains(&(ask_count as usize))
+        {
+            return Err(EscrowError::InvalidBundle.into());
+        }
+        Ok(())
+    }
+
+    // Create the bundle, then a vault per offered mint holding what the maker deposits
+    #[inline]
+    pub fn init<'a>(
+        seed: u64,
+        expiry: i64,
+        taker: Pubkey,
+        offer_amounts: &[u64],
+        ask_amounts: &[u64],
+        maker: &AccountInfo<'a>,
+        bundle: &AccountInfo<'a>,
+        offer_accounts: &[AccountInfo<'a>],
+        ask_accounts: &[AccountInfo<'a>],
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let seed_bytes = seed.to_le_bytes();
+        let bump = check_eq_pda_and_get_bump(
+            &[b"bundle", maker.key.as_ref(), &seed_bytes],
+            &crate::ID,
+            bundle.key,
+        )?;
+        let space = core::mem::size_of::<Bundle>();
+        let rent = Rent::get()?.minimum_balance(space);
+        invoke_signed(
+            &create_account(maker.key, bundle.key, rent, space as u64, &crate::ID),
+            &[maker.clone(), bundle.clone()],
+            &[&[b"bundle", maker.key.as_ref(), &seed_bytes, &[bump]]],
+        )?;
+
+        let mut bundle_data = Bundle {
+            seed,
+            maker: *maker.key,
+            taker,
+            expiry,
+            offer_count: offer_amounts.len() as u8,
+            ask_count: ask_amounts.len() as u8,
+            ..Zeroable::zeroed()
+        };
+
+        for (i, (leg, &amount)) in offer_accounts
+            .chunks_exact(MAKE_OFFER_ACCOUNTS)
+            .zip(offer_amounts)
+            .enumerate()
+        {
+            let [mint, maker_ta, vault, token_program] = leg else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            // Native SOL has no vault to sit in
+            if is_native(mint.key) || amount == 0 {
+                return Err(EscrowError::InvalidBundle.into());
+            }
+            let vault_bump = check_eq_pda_and_get_bump(
+                &[b"bundle_vault", bundle.key.as_ref(), mint.key.as_ref()],
+                &crate::ID,
+                vault.key,
+            )?;
+            // A repeated mint fails here, as its vault already exists
+            create_token_account(
+                maker,
+                vault,
+                mint,
+                bundle.key,
+                token_program.key,
+                &[&[
+                    b"bundle_vault",
+                    bundle.key.as_ref(),
+                    mint.key.as_ref(),
+                    &[vault_bump],
+                ]],
+            )?;
+            transfer(
+                token_program.key,
+                mint,
+                maker_ta,
+                vault,
+                maker,
+                remaining_accounts,
+                amount,
+                &[],
+            )?;
+            // Track what landed after transfer fees
+            bundle_data.offers[i] = Leg {
+                mint: *mint.key,
+                amount: get_amount(vault)?,
+            };
+        }
+
+        for (i, (leg, &amount)) in ask_accounts
+            .chunks_exact(MAKE_ASK_ACCOUNTS)
+            .zip(ask_amounts)
+            .enumerate()
+        {
+            let [mint, token_program] = leg else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            if is_native(mint.key) || amount == 0 {
+                return Err(EscrowError::InvalidBundle.into());
+            }
+            // Make sure the offer can be filled
+            check_token_program(token_program.key, mint)?;
+            bundle_data.asks[i] = Leg {
+                mint: *mint.key,
+                amount,
+            };
+        }
+
+        let mut data = bundle.try_borrow_mut_data()?;
+        *bytemuck::try_from_bytes_mut::<Bundle>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)? = bundle_data;
+        Ok(())
+    }
+
+    // Move every offered token to the taker and every asked token to the maker,
+    // charging the protocol fee on each leg of the configured side
+    #[inline]
+    pub fn take<'a>(
+        bundle_data: Bundle,
+        bump: u8,
+        taker: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        bundle: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        offer_accounts: &[AccountInfo<'a>],
+        ask_accounts: &[AccountInfo<'a>],
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if bundle_data.is_expired(Clock::get()?.unix_timestamp) {
+            return Err(EscrowError::Expired.into());
+        }
+        if !taker.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if bundle_data.taker != Pubkey::default() && bundle_data.taker != *taker.key {
+            return Err(EscrowError::InvalidTaker.into());
+        }
+
+        let config_data = Config::get(config)?;
+        let fee = |leg_a: bool, amount: u64| match config_data {
+            Some(config_data) if (config_data.fee_leg == FEE_LEG_A) == leg_a => {
+                config_data.fee(amount)
+            }
+            _ => 0,
+        };
+        let check_treasury = |treasury_ta: &AccountInfo| -> ProgramResult {
+            match config_data {
+                Some(config_data) if config_data.treasury == get_owner(treasury_ta)? => Ok(()),
+                _ => Err(EscrowError::InvalidTreasury.into()),
+            }
+        };
+
+        let seed = bundle_data.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"bundle", maker.key.as_ref(), &seed, &[bump]]];
+
+        for (leg, offer) in offer_accounts
+            .chunks_exact(TAKE_OFFER_ACCOUNTS)
+            .zip(bundle_data.offers())
+        {
+            let [mint, vault, taker_ta, treasury_ta, token_program] = leg else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            if *mint.key != offer.mint {
+                return Err(EscrowError::MintMismatch.into());
+            }
+            check_eq_pda(
+                &[b"bundle_vault", bundle.key.as_ref(), mint.key.as_ref()],
+                &crate::ID,
+                vault.key,
+            )?;
+            check_token_program(token_program.key, mint)?;
+            init_ata_if_needed(taker, taker_ta, taker, mint, system_program, token_program)?;
+
+            // Sweep the whole vault so it can be closed
+            let amount = get_amount(vault)?;
+            let fee_a = fee(true, amount);
+            transfer(
+                token_program.key,
+                mint,
+                vault,
+                taker_ta,
+                bundle,
+                remaining_accounts,
+                amount - fee_a,
+                signer_seeds,
+            )?;
+            if fee_a > 0 {
+                check_treasury(treasury_ta)?;
+                transfer(
+                    token_program.key,
+                    mint,
+                    vault,
+                    treasury_ta,
+                    bundle,
+                    remaining_accounts,
+                    fee_a,
+                    signer_seeds,
+                )?;
+            }
+            close_token_account(token_program.key, mint, vault, maker, bundle, signer_seeds)?;
+        }
+
+        for (leg, ask) in ask_accounts
+            .chunks_exact(TAKE_ASK_ACCOUNTS)
+            .zip(bundle_data.asks())
+        {
+            let [mint, taker_ta, maker_ta, treasury_ta, token_program] = leg else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            if *mint.key != ask.mint {
+                return Err(EscrowError::MintMismatch.into());
+            }
+            check_token_program(token_program.key, mint)?;
+            init_ata_if_needed(taker, maker_ta, maker, mint, system_program, token_program)?;
+            // An existing account is passed through as is, so it must be the maker's
+            if *maker.key != get_owner(maker_ta)? {
+                return Err(EscrowError::InvalidMakerTokenAccount.into());
+            }
+
+            // Grossed up so the maker receives the full ask after transfer fees
+            transfer(
+                token_program.key,
+                mint,
+                taker_ta,
+                maker_ta,
+                taker,
+                remaining_accounts,
+                add_transfer_fee(mint, ask.amount)?,
+                &[],
+            )?;
+            let fee_b = fee(false, ask.amount);
+            if fee_b > 0 {
+                check_treasury(treasury_ta)?;
+                transfer(
+                    token_program.key,
+                    mint,
+                    taker_ta,
+                    treasury_ta,
+                    taker,
+                    remaining_accounts,
+                    fee_b,
+                    &[],
+                )?;
+            }
+        }
+
+        Self::close(bundle, maker)
+    }
+
+    // Return every offered token to the maker and close the bundle
+    #[inline]
+    pub fn refund<'a>(
+        bundle_data: Bundle,
+        bump: u8,
+        maker: &AccountInfo<'a>,
+        bundle: &AccountInfo<'a>,
+        offer_accounts: &[AccountInfo<'a>],
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let seed = bundle_data.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"bundle", maker.key.as_ref(), &seed, &[bump]]];
+
+        for (leg, offer) in offer_accounts
+            .chunks_exact(REFUND_OFFER_ACCOUNTS)
+            .zip(bundle_data.offers())
+        {
+            let [mint, maker_ta, vault, token_program] = leg else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            if *mint.key != offer.mint {
+                return Err(EscrowError::MintMismatch.into());
+            }
+            check_eq_pda(
+                &[b"bundle_vault", bundle.key.as_ref(), mint.key.as_ref()],
+                &crate::ID,
+                vault.key,
+            )?;
+            // Refunds can be cranked by anyone, so tokens must go back to the maker
+            if *maker.key != get_owner(maker_ta)? {
+                return Err(EscrowError::InvalidMakerTokenAccount.into());
+            }
+            check_token_program(token_program.key, mint)?;
+
+            transfer(
+                token_program.key,
+                mint,
+                vault,
+                maker_ta,
+                bundle,
+                remaining_accounts,
+                get_amount(vault)?,
+                signer_seeds,
+            )?;
+            close_token_account(token_program.key, mint, vault, maker, bundle, signer_seeds)?;
+        }
+
+        Self::close(bundle, maker)
+    }
+
+    #[inline]
+    fn close(bundle: &AccountInfo, maker: &AccountInfo) -> ProgramResult {
+        let balance = bundle.lamports();
+        bundle.realloc(0, false)?;
+        **bundle.lamports.borrow_mut() = 0;
+        **maker.lamports.borrow_mut() += balance;
+        bundle.assign(&Pubkey::default());
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use super::utils::{check_eq_pda, check_eq_pda_and_get_bump};
+use crate::error::EscrowError;
+use bytemuck::{Pod, Zeroable};
+use shank::ShankAccount;
+use solana_program::{
+    account_info::AccountInfo, bpf_loader_upgradeable, entrypoint::ProgramResult,
+    program::invoke_signed, program_error::ProgramError, pubkey::Pubkey, rent::Rent,
+    system_instruction::create_account, sysvar::Sysvar,
+};
+
+// Fee is paid by the taker in token B, on top of the maker's ask
+pub const FEE_LEG_B: u8 = 0;
+// Fee is withheld from the token A sent to the taker
+pub const FEE_LEG_A: u8 = 1;
+
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, ShankAccount)]
+pub struct Config {
+    pub admin: Pubkey,
+    // Owner of the token accounts that collect fees
+    pub treasury: Pubkey,
+    pub fee_bps: u16,
+    pub fee_leg: u8,
+    pub bump: u8,
+    #[padding]
+    pub _padding: [u8; 4],
+}
+
+impl Config {
+    // The config is optional: fills are free until an admin initializes it
+    #[inline]
+    pub fn get(config: &AccountInfo) -> Result<Option<Config>, ProgramError> {
+        check_eq_pda(&[b"config"], &crate::ID, config.key)?;
+        if config.data_is_empty() {
+            return Ok(None);
+        }
+        if config.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let config_data = *bytemuck::try_from_bytes::<Config>(*config.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        Ok(Some(config_data))
+    }
+
+    // Fee owed on `amount` of the charged leg, rounded up
+    #[inline]
+    pub fn fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128).div_ceil(MAX_FEE_BPS as u128) as u64
+    }
+
+    #[inline]
+    pub fn check_fee(fee_bps: u16, fee_leg: u8) -> ProgramResult {
+        if fee_bps > MAX_FEE_BPS || ![FEE_LEG_A, FEE_LEG_B].contains(&fee_leg) {
+            return Err(EscrowError::InvalidFee.into());
+        }
+        Ok(())
+    }
+
+    // Only the program's upgrade authority may create the config
+    #[inline]
+    pub fn check_upgrade_authority(
+        admin: &AccountInfo,
+        program_data: &AccountInfo,
+    ) -> ProgramResult {
+        check_eq_pda(
+            &[crate::ID.as_ref()],
+            &bpf_loader_upgradeable::ID,
+            program_data.key,
+        )?;
+        if program_data.owner != &bpf_loader_upgradeable::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        // UpgradeableLoaderState::ProgramData { slot: u64, upgrade_authority_address: Option<Pubkey> }
+        let data = program_data.try_borrow_data()?;
+        let (tag, rest) = data
+            .split_at_checked(4)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        if tag != 3u32.to_le_bytes() || rest.get(8) != Some(&1) {
+            return Err(EscrowError::InvalidAdmin.into());
+        }
+        if rest.get(9..41) != Some(admin.key.as_ref()) {
+            return Err(EscrowError::InvalidAdmin.into());
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn init<'a>(
+        fee_bps: u16,
+        fee_leg: u8,
+        admin: &AccountInfo<'a>,
+        treasury: &Pubkey,
+        config: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        Self::check_fee(fee_bps, fee_leg)?;
+        let bump = check_eq_pda_and_get_bump(&[b"config"], &crate::ID, config.key)?;
+        let space = core::mem::size_of::<Config>();
+        let rent = Rent::get()?.minimum_balance(space);
+
+        invoke_signed(
+            &create_account(admin.key, config.key, rent, space as u64, &crate::ID),
+            &[admin.clone(), config.clone()],
+            &[&[b"config", &[bump]]],
+        )?;
+
+        let mut data = config.try_borrow_mut_data()?;
+        let config_data = bytemuck::try_from_bytes_mut::<Config>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        *config_data = Config {
+            admin: *admin.key,
+            treasury: *treasury,
+            fee_bps,
+            fee_leg,
+            bump,
+            _padding: [0; 4],
+        };
+        Ok(())
+    }
+
+    #[inline]
+    pub fn update(
+        fee_bps: u16,
+        fee_leg: u8,
+        admin: &AccountInfo,
+        new_admin: &Pubkey,
+        treasury: &Pubkey,
+        config: &AccountInfo,
+    ) -> ProgramResult {
+        Self::check_fee(fee_bps, fee_leg)?;
+        let Some(current) = Self::get(config)? else {
+            return Err(ProgramError::UninitializedAccount);
+        };
+        if !admin.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if current.admin != *admin.key {
+            return Err(EscrowError::InvalidAdmin.into());
+        }
+
+        let mut data = config.try_borrow_mut_data()?;
+        let config_data = bytemuck::try_from_bytes_mut::<Config>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        config_data.admin = *new_admin;
+        config_data.treasury = *treasury;
+        config_data.fee_bps = fee_bps;
+        config_data.fee_leg = fee_leg;
+        Ok(())
+    }
+}
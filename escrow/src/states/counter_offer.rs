@@ -0,0 +1,450 @@
+use super::config::{Config, FEE_LEG_A};
+use super::state::Escrow;
+use super::utils::{
+    check_eq_pda, check_eq_pda_and_get_bump, check_token_program, close_token_account, get_amount,
+    get_owner, init_ata_if_needed, is_native, move_lamports, transfer,
+};
+use crate::{
+    error::EscrowError,
+    events::{Event, TakeEvent, WithdrawCounterOfferEvent},
+};
+use bytemuck::{Pod, Zeroable};
+use shank::ShankAccount;
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction::create_account,
+    sysvar::Sysvar,
+};
+
+// A taker's proposal to fill an escrow on different terms, at
+// ["counter", escrow, taker]. The token B offered sits in the usual vault at
+// ["vault", counter]; native SOL is held by the account itself.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankAccount)]
+pub struct CounterOffer {
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    pub mint_b: Pubkey,
+    // Token A the taker wants out of the escrow
+    pub amount_a: u64,
+    // Token B deposited in return, net of any transfer fee
+    pub amount_b: u64,
+}
+
+impl CounterOffer {
+    #[inline]
+    pub fn get_data_and_bump(counter: &AccountInfo) -> Result<(CounterOffer, u8), ProgramError> {
+        if counter.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let counter_data = *bytemuck::try_from_bytes::<CounterOffer>(*counter.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+
+        let bump = check_eq_pda_and_get_bump(
+            &[
+                b"counter",
+                counter_data.escrow.as_ref(),
+                counter_data.taker.as_ref(),
+            ],
+            &crate::ID,
+            counter.key,
+        )?;
+        Ok((counter_data, bump))
+    }
+
+    // Create the counter-offer and take the taker's token B into custody
+    #[inline]
+    pub fn init<'a>(
+        amount_a: u64,
+        amount_b: u64,
+        token_program: &Pubkey,
+        taker: &AccountInfo<'a>,
+        taker_ta_b: &AccountInfo<'a>,
+        mint_b: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        counter: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let escrow_data = *Escrow::load(escrow)?;
+        if *mint_b.key != escrow_data.mint_b {
+            return Err(EscrowError::MintMismatch.into());
+        }
+        if escrow_data.is_expired(Clock::get()?.unix_timestamp) {
+            return Err(EscrowError::Expired.into());
+        }
+        // Private offers only take counter-offers from the designated taker
+        if escrow_data.taker != Pubkey::default() && escrow_data.taker != *taker.key {
+            return Err(EscrowError::InvalidTaker.into());
+        }
+        if amount_a == 0 || amount_a > escrow_data.remaining || amount_b == 0 {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+
+        let bump = check_eq_pda_and_get_bump(
+            &[b"counter", escrow.key.as_ref(), taker.key.as_ref()],
+            &crate::ID,
+            counter.key,
+        )?;
+        let space = core::mem::size_of::<CounterOffer>();
+        let rent = Rent::get()?.minimum_balance(space);
+        invoke_signed(
+            &create_account(taker.key, counter.key, rent, space as u64, &crate::ID),
+            &[taker.clone(), counter.clone()],
+            &[&[b"counter", escrow.key.as_ref(), taker.key.as_ref(), &[bump]]],
+        )?;
+
+        let taker_ta_b = if is_native(mint_b.key) {
+            taker
+        } else {
+            Escrow::create_vault(counter.key, token_program, mint_b, vault, taker)?;
+            taker_ta_b
+        };
+        let amount_b = Escrow::deposit(
+            counter,
+            token_program,
+            amount_b,
+            taker_ta_b,
+            mint_b,
+            vault,
+            taker,
+            remaining_accounts,
+        )?;
+
+        let mut data = counter.try_borrow_mut_data()?;
+        let counter_data = bytemuck::try_from_bytes_mut::<CounterOffer>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        *counter_data = CounterOffer {
+            escrow: *escrow.key,
+            taker: *taker.key,
+            mint_b: *mint_b.key,
+            amount_a,
+            amount_b,
+        };
+        Ok(())
+    }
+
+    // Send token B out of custody, signed by the counter-offer PDA
+    #[inline]
+    fn release<'a>(
+        token_program: &Pubkey,
+        mint_b: &AccountInfo<'a>,
+        counter: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        to: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if is_native(mint_b.key) {
+            return move_lamports(counter, to, amount);
+        }
+        transfer(
+            token_program,
+            mint_b,
+            vault,
+            to,
+            counter,
+            remaining_accounts,
+            amount,
+            signer_seeds,
+        )
+    }
+
+    // Close the vault, if any, and then the counter-offer, returning all rent
+    // and any native deposit left to the taker
+    #[inline]
+    fn close<'a>(
+        token_program: &Pubkey,
+        mint_b: &AccountInfo<'a>,
+        counter: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        taker: &AccountInfo<'a>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if !is_native(mint_b.key) {
+            close_token_account(token_program, mint_b, vault, taker, counter, signer_seeds)?;
+        }
+
+        let balance = counter.lamports();
+        counter.realloc(0, false)?;
+        **counter.lamports.borrow_mut() = 0;
+        **taker.lamports.borrow_mut() += balance;
+        counter.assign(&Pubkey::default());
+        Ok(())
+    }
+
+    // The maker fills the escrow on the counter-offer's terms: `amount_a` of
+    // token A goes to the taker and the deposited token B to the maker, with the
+    // protocol fee withheld on the configured leg
+    #[inline]
+    pub fn accept<'a>(
+        counter_data: CounterOffer,
+        counter_bump: u8,
+        escrow_data: Escrow,
+        escrow_bump: u8,
+        token_program_a: &AccountInfo<'a>,
+        token_program_b: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        taker: &AccountInfo<'a>,
+        mint_a: &AccountInfo<'a>,
+        mint_b: &AccountInfo<'a>,
+        maker_ta_b: &AccountInfo<'a>,
+        taker_ta_a: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        counter: &AccountInfo<'a>,
+        counter_vault: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        treasury_ta: &AccountInfo<'a>,
+        registry: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if !maker.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if counter_data.escrow != *escrow.key || counter_data.taker != *taker.key {
+            return Err(EscrowError::InvalidCounterOffer.into());
+        }
+        if *mint_a.key != escrow_data.mint_a || *mint_b.key != escrow_data.mint_b {
+            return Err(EscrowError::MintMismatch.into());
+        }
+        // The escrow may have been partly filled or withdrawn from since
+        if counter_data.amount_a > escrow_data.remaining {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+
+        let native_a = is_native(mint_a.key);
+        let native_b = is_native(mint_b.key);
+        if !native_a {
+            check_eq_pda(&[b"vault", escrow.key.as_ref()], &crate::ID, vault.key)?;
+        }
+        if !native_b {
+            check_eq_pda(
+                &[b"vault", counter.key.as_ref()],
+                &crate::ID,
+                counter_vault.key,
+            )?;
+        }
+        check_token_program(token_program_a.key, mint_a)?;
+        check_token_program(token_program_b.key, mint_b)?;
+        let taker_ta_a = if native_a { taker } else { taker_ta_a };
+        let maker_ta_b = if native_b { maker } else { maker_ta_b };
+
+        // Create the taker's token A and maker's token B accounts if missing, paid for by the maker
+        init_ata_if_needed(
+            maker,
+            taker_ta_a,
+            taker,
+            mint_a,
+            system_program,
+            token_program_a,
+        )?;
+        init_ata_if_needed(
+            maker,
+            maker_ta_b,
+            maker,
+            mint_b,
+            system_program,
+            token_program_b,
+        )?;
+        // The maker submits the accept, so token A must reach the taker who offered
+        if !native_a && get_owner(taker_ta_a)? != *taker.key {
+            return Err(EscrowError::InvalidTakerTokenAccount.into());
+        }
+
+        // A fixed ask shrinks by the share of it the filled token A stood for
+        let filled = counter_data.amount_a == escrow_data.remaining;
+        let cost = if escrow_data.pricing.is_fixed() && !filled {
+            escrow_data.fill_cost(counter_data.amount_a)?
+        } else {
+            0
+        };
+        // The last fill sweeps the whole vault so it can be closed
+        let amount_a = if filled && !native_a {
+            get_amount(vault)?
+        } else {
+            counter_data.amount_a
+        };
+        let amount_b = if native_b {
+            counter_data.amount_b
+        } else {
+            get_amount(counter_vault)?
+        };
+
+        let config_data = Config::get(config)?;
+        let (fee_a, fee_b) = match config_data {
+            Some(config_data) if config_data.fee_leg == FEE_LEG_A => (config_data.fee(amount_a), 0),
+            Some(config_data) => (0, config_data.fee(amount_b)),
+            None => (0, 0),
+        };
+        if let Some(config_data) = config_data.filter(|_| fee_a + fee_b > 0) {
+            // Native SOL fees go straight to the treasury wallet
+            let native_fee = if fee_a > 0 { native_a } else { native_b };
+            let treasury = if native_fee {
+                *treasury_ta.key
+            } else {
+                get_owner(treasury_ta)?
+            };
+            if config_data.treasury != treasury {
+                return Err(EscrowError::InvalidTreasury.into());
+            }
+        }
+
+        let seed = escrow_data.seed.to_le_bytes();
+        let escrow_seeds: &[&[&[u8]]] = &[&[b"escrow", maker.key.as_ref(), &seed, &[escrow_bump]]];
+        let counter_seeds: &[&[&[u8]]] = &[&[
+            b"counter",
+            escrow.key.as_ref(),
+            taker.key.as_ref(),
+            &[counter_bump],
+        ]];
+
+        for (to, amount) in [(taker_ta_a, amount_a - fee_a), (treasury_ta, fee_a)] {
+            if amount > 0 {
+                Escrow::release(
+                    token_program_a.key,
+                    mint_a,
+                    escrow,
+                    vault,
+                    to,
+                    remaining_accounts,
+                    amount,
+                    escrow_seeds,
+                )?;
+            }
+        }
+        for (to, amount) in [(maker_ta_b, amount_b - fee_b), (treasury_ta, fee_b)] {
+            if amount > 0 {
+                Self::release(
+                    token_program_b.key,
+                    mint_b,
+                    counter,
+                    counter_vault,
+                    to,
+                    remaining_accounts,
+                    amount,
+                    counter_seeds,
+                )?;
+            }
+        }
+        Self::close(
+            token_program_b.key,
+            mint_b,
+            counter,
+            counter_vault,
+            taker,
+            counter_seeds,
+        )?;
+
+        TakeEvent {
+            escrow: *escrow.key,
+            maker: *maker.key,
+            taker: *taker.key,
+            mint_a: *mint_a.key,
+            mint_b: *mint_b.key,
+            amount: amount_a,
+            paid: amount_b - fee_b,
+            fee_a,
+            fee_b,
+            remaining: if filled {
+                0
+            } else {
+                escrow_data.remaining - amount_a
+            },
+        }
+        .emit();
+
+        // Partial fill: keep the escrow open with what is left
+        if !filled {
+            let mut escrow_state = Escrow::load_mut(escrow)?;
+            escrow_state.remaining -= amount_a;
+            escrow_state.receive -= cost;
+            return Ok(());
+        }
+
+        Escrow::deregister(&escrow_data, escrow, registry)?;
+        Escrow::close(
+            token_program_a.key,
+            mint_a,
+            escrow,
+            vault,
+            maker,
+            escrow_seeds,
+        )
+    }
+
+    // Either the taker or the escrow's maker can call off a counter-offer,
+    // returning the deposit and rent to the taker
+    #[inline]
+    pub fn withdraw<'a>(
+        counter_data: CounterOffer,
+        bump: u8,
+        token_program: &Pubkey,
+        authority: &AccountInfo<'a>,
+        taker: &AccountInfo<'a>,
+        mint_b: &AccountInfo<'a>,
+        taker_ta_b: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        counter: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if counter_data.taker != *taker.key || counter_data.escrow != *escrow.key {
+            return Err(EscrowError::InvalidCounterOffer.into());
+        }
+        // The escrow is only read when the maker rejects, as it may be closed already
+        if *authority.key != counter_data.taker && Escrow::load(escrow)?.maker != *authority.key {
+            return Err(EscrowError::InvalidCounterOffer.into());
+        }
+        if *mint_b.key != counter_data.mint_b {
+            return Err(EscrowError::MintMismatch.into());
+        }
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"counter",
+            counter_data.escrow.as_ref(),
+            counter_data.taker.as_ref(),
+            &[bump],
+        ]];
+        // Native SOL is returned with the counter-offer's own lamports when it closes
+        let amount = if is_native(mint_b.key) {
+            counter_data.amount_b
+        } else {
+            check_eq_pda(&[b"vault", counter.key.as_ref()], &crate::ID, vault.key)?;
+            check_token_program(token_program, mint_b)?;
+            // The maker can crank this, so token B must go back to the taker
+            if get_owner(taker_ta_b)? != *taker.key {
+                return Err(EscrowError::InvalidCounterOffer.into());
+            }
+            let amount = get_amount(vault)?;
+            Self::release(
+                token_program,
+                mint_b,
+                counter,
+                vault,
+                taker_ta_b,
+                remaining_accounts,
+                amount,
+                signer_seeds,
+            )?;
+            amount
+        };
+
+        WithdrawCounterOfferEvent {
+            counter: *counter.key,
+            escrow: *escrow.key,
+            taker: *taker.key,
+            authority: *authority.key,
+            mint_b: *mint_b.key,
+            amount,
+        }
+        .emit();
+
+        Self::close(token_program, mint_b, counter, vault, taker, signer_seeds)
+    }
+}
@@ -0,0 +1,247 @@
+use super::config::Config;
+use super::state::Escrow;
+use super::utils::{
+    add_transfer_fee, check_eq_pda, check_token_program, get_amount, get_owner, is_native,
+};
+use crate::{
+    error::EscrowError,
+    events::{Event, TakeEvent},
+};
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, pubkey::Pubkey, sysvar::Sysvar,
+};
+
+// One of the two escrows in a match, with the accounts its side settles through
+pub struct MatchSide<'a, 'info> {
+    pub escrow_data: Escrow,
+    pub bump: u8,
+    pub maker: &'a AccountInfo<'info>,
+    // Mint this escrow offers
+    pub mint: &'a AccountInfo<'info>,
+    pub escrow: &'a AccountInfo<'info>,
+    pub vault: &'a AccountInfo<'info>,
+    // Maker's account for the other side's mint
+    pub maker_ta: &'a AccountInfo<'info>,
+    // Cranker's account for this side's mint, or this program's id for no bounty
+    pub bounty_ta: &'a AccountInfo<'info>,
+    // Treasury's account for this side's mint, used if this side is filled
+    pub treasury_ta: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub registry: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> MatchSide<'a, 'info> {
+    #[inline]
+    fn check(&self, other: &MatchSide, now: i64) -> ProgramResult {
+        let escrow_data = &self.escrow_data;
+        if escrow_data.mint_a != *self.mint.key
+            || escrow_data.mint_b != *other.mint.key
+            || !escrow_data.pricing.is_fixed()
+        {
+            return Err(EscrowError::PricesDoNotCross.into());
+        }
+        if escrow_data.is_expired(now) {
+            return Err(EscrowError::Expired.into());
+        }
+        // A private offer only matches against its designated taker's escrow
+        if escrow_data.taker != Pubkey::default() && escrow_data.taker != other.escrow_data.maker {
+            return Err(EscrowError::InvalidTaker.into());
+        }
+        if !is_native(self.mint.key) {
+            check_eq_pda(
+                &[b"vault", self.escrow.key.as_ref()],
+                &crate::ID,
+                self.vault.key,
+            )?;
+        }
+        check_token_program(self.token_program.key, self.mint)?;
+        // Matches can be cranked by anyone, so proceeds must go to the maker
+        if !is_native(other.mint.key) && get_owner(self.maker_ta)? != *self.maker.key {
+            return Err(EscrowError::InvalidMakerTokenAccount.into());
+        }
+        Ok(())
+    }
+
+    // Where the maker is paid in the other side's mint
+    #[inline]
+    fn payee(&self, other: &MatchSide<'a, 'info>) -> &'a AccountInfo<'info> {
+        if is_native(other.mint.key) {
+            self.maker
+        } else {
+            self.maker_ta
+        }
+    }
+
+    // Amount of this side's mint to send so that `amount` arrives
+    #[inline]
+    fn gross(&self, amount: u64) -> Result<u64, ProgramError> {
+        if is_native(self.mint.key) {
+            return Ok(amount);
+        }
+        add_transfer_fee(self.mint, amount)
+    }
+
+    // Token A left in custody, including anything sent to the vault directly
+    #[inline]
+    fn held(&self) -> Result<u64, ProgramError> {
+        if is_native(self.mint.key) {
+            return Ok(self.escrow_data.remaining);
+        }
+        get_amount(self.vault)
+    }
+
+    #[inline]
+    fn release(
+        &self,
+        to: &AccountInfo<'info>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+        let seed = self.escrow_data.seed.to_le_bytes();
+        Escrow::release(
+            self.token_program.key,
+            self.mint,
+            self.escrow,
+            self.vault,
+            to,
+            remaining_accounts,
+            amount,
+            &[&[b"escrow", self.maker.key.as_ref(), &seed, &[self.bump]]],
+        )
+    }
+
+    #[inline]
+    fn close(&self) -> ProgramResult {
+        let seed = self.escrow_data.seed.to_le_bytes();
+        Escrow::deregister(&self.escrow_data, self.escrow, self.registry)?;
+        Escrow::close(
+            self.token_program.key,
+            self.mint,
+            self.escrow,
+            self.vault,
+            self.maker,
+            &[&[b"escrow", self.maker.key.as_ref(), &seed, &[self.bump]]],
+        )
+    }
+}
+
+// Settle two escrows of opposite mint pairs whose fixed prices cross, straight
+// from their vaults. One escrow is filled completely at exactly its ask and
+// the other pays it, receiving at least its own pro-rata ask in return. What
+// the filled escrow offers beyond that is the surplus: a bounty for the cranker
+// if it gave an account for it, an improved price for the other maker if not.
+// Each escrow logs a fill with the cranker as its taker.
+// Neither maker pays more than their ask, so the protocol fee on both fills
+// comes out of the surplus, whichever leg the config names: on what the filled
+// escrow releases and on what the other receives, both in the filled escrow's
+// mint. Prices that cross by less than the fee don't match.
+#[inline]
+pub fn cross<'a, 'info>(
+    cranker: &AccountInfo<'info>,
+    config: &AccountInfo<'info>,
+    x: MatchSide<'a, 'info>,
+    y: MatchSide<'a, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> ProgramResult {
+    if x.escrow.key == y.escrow.key {
+        return Err(EscrowError::PricesDoNotCross.into());
+    }
+    let now = Clock::get()?.unix_timestamp;
+    x.check(&y, now)?;
+    y.check(&x, now)?;
+
+    // Fill whichever escrow's whole ask the other can pay
+    let (filled, partial) = if y.gross(x.escrow_data.receive)? <= y.escrow_data.remaining {
+        (x, y)
+    } else {
+        (y, x)
+    };
+    let pay = partial.gross(filled.escrow_data.receive)?;
+    if pay > partial.escrow_data.remaining {
+        return Err(EscrowError::PricesDoNotCross.into());
+    }
+    let cost = partial.escrow_data.fill_cost(pay)?;
+    let cost_paid = filled.gross(cost)?;
+    if cost_paid > filled.escrow_data.remaining {
+        return Err(EscrowError::PricesDoNotCross.into());
+    }
+    let held = filled.held()?;
+    let config_data = Config::get(config)?;
+    let (fee_filled, fee_partial) = match config_data {
+        Some(config_data) => (config_data.fee(held), config_data.fee(cost_paid)),
+        None => (0, 0),
+    };
+    let fee = fee_filled + fee_partial;
+    let surplus = (held - cost_paid)
+        .checked_sub(fee)
+        .ok_or(EscrowError::PricesDoNotCross)?;
+    if let Some(config_data) = config_data.filter(|_| fee > 0) {
+        // Native SOL fees go straight to the treasury wallet
+        let treasury = if is_native(filled.mint.key) {
+            *filled.treasury_ta.key
+        } else {
+            get_owner(filled.treasury_ta)?
+        };
+        if config_data.treasury != treasury {
+            return Err(EscrowError::InvalidTreasury.into());
+        }
+    }
+    // A partial escrow filled exactly sweeps its vault too, so it can be closed
+    let partial_done = pay == partial.escrow_data.remaining;
+    let pay = if partial_done { partial.held()? } else { pay };
+
+    filled.release(partial.payee(&filled), cost_paid, remaining_accounts)?;
+    let bounty_ta = if *filled.bounty_ta.key == crate::ID {
+        partial.payee(&filled)
+    } else {
+        filled.bounty_ta
+    };
+    filled.release(bounty_ta, surplus, remaining_accounts)?;
+    filled.release(filled.treasury_ta, fee, remaining_accounts)?;
+    partial.release(filled.payee(&partial), pay, remaining_accounts)?;
+
+    TakeEvent {
+        escrow: *filled.escrow.key,
+        maker: *filled.maker.key,
+        taker: *cranker.key,
+        mint_a: *filled.mint.key,
+        mint_b: *partial.mint.key,
+        amount: held,
+        paid: filled.escrow_data.receive,
+        fee_a: fee_filled,
+        fee_b: 0,
+        remaining: 0,
+    }
+    .emit();
+    TakeEvent {
+        escrow: *partial.escrow.key,
+        maker: *partial.maker.key,
+        taker: *cranker.key,
+        mint_a: *partial.mint.key,
+        mint_b: *filled.mint.key,
+        amount: pay,
+        paid: cost,
+        fee_a: 0,
+        fee_b: fee_partial,
+        remaining: if partial_done {
+            0
+        } else {
+            partial.escrow_data.remaining - pay
+        },
+    }
+    .emit();
+
+    filled.close()?;
+
+    if partial_done {
+        return partial.close();
+    }
+    let mut escrow_state = Escrow::load_mut(partial.escrow)?;
+    escrow_state.remaining -= pay;
+    escrow_state.receive -= cost;
+    Ok(())
+}
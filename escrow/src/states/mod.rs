@@ -1,5 +1,21 @@
+pub mod bundle;
+pub mod config;
+pub mod counter_offer;
+pub mod matcher;
+pub mod price_feed;
+pub mod registry;
+pub mod service;
 pub mod state;
 pub mod utils;
+pub mod vesting;
 
+pub use bundle::*;
+pub use config::*;
+pub use counter_offer::*;
+pub use matcher::*;
+pub use price_feed::*;
+pub use registry::*;
+pub use service::*;
 pub use state::*;
 pub use utils::*;
+pub use vesting::*;
@@ -0,0 +1,130 @@
+use crate::error::EscrowError;
+use bytemuck::{Pod, Zeroable};
+use shank::{ShankAccount, ShankType};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+pub const PRICE_FEED_MAGIC: [u8; 8] = *b"escrowpx";
+// Widest exponent accepted from a feed, keeping quotes within u128
+pub const MAX_PRICE_EXPONENT: i32 = 18;
+
+// Price account read by oracle-priced escrows. Any program may publish one; the
+// maker trusts a feed by naming its address. Little-endian layout, 40 bytes:
+//   0..8   magic, PRICE_FEED_MAGIC
+//   8..16  price: i64, token B base units per token A base unit, times 10^exponent
+//  16..24  conf: u64, confidence interval around `price`, same units
+//  24..28  exponent: i32
+//  28..32  padding
+//  32..40  publish_time: i64, unix timestamp of the last update
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankAccount)]
+pub struct PriceFeed {
+    pub magic: [u8; 8],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    #[padding]
+    pub _padding: [u8; 4],
+    pub publish_time: i64,
+}
+
+impl PriceFeed {
+    #[inline]
+    pub fn get(price_feed: &AccountInfo) -> Result<PriceFeed, ProgramError> {
+        let data = price_feed.try_borrow_data()?;
+        let feed = bytemuck::try_pod_read_unaligned::<PriceFeed>(
+            data.get(..core::mem::size_of::<PriceFeed>())
+                .ok_or(EscrowError::InvalidPriceFeed)?,
+        )
+        .map_err(|_| EscrowError::InvalidPriceFeed)?;
+        if feed.magic != PRICE_FEED_MAGIC
+            || feed.price <= 0
+            || feed.exponent.abs() > MAX_PRICE_EXPONENT
+        {
+            return Err(EscrowError::InvalidPriceFeed.into());
+        }
+        Ok(feed)
+    }
+}
+
+// How an escrow's ask tracks a price feed. A default `oracle` means the fixed
+// `receive` ask applies instead.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable, ShankType)]
+pub struct OraclePricing {
+    pub oracle: Pubkey,
+    // Premium over the feed price, in basis points
+    pub spread_bps: u16,
+    // Widest accepted confidence interval, in basis points of the price
+    pub max_conf_bps: u16,
+    #[padding]
+    pub _padding: [u8; 4],
+    // Oldest accepted feed update, in seconds
+    pub max_age: i64,
+    // Bounds on the price after the spread, in the feed's units, 0 for none
+    pub min_price: u64,
+    pub max_price: u64,
+}
+
+impl OraclePricing {
+    #[inline]
+    pub fn is_fixed(&self) -> bool {
+        self.oracle == Pubkey::default()
+    }
+
+    #[inline]
+    pub fn check(&self) -> ProgramResult {
+        if self.is_fixed() {
+            return Ok(());
+        }
+        if self.max_age <= 0 || (self.max_price != 0 && self.min_price > self.max_price) {
+            return Err(EscrowError::InvalidPricing.into());
+        }
+        Ok(())
+    }
+
+    // Token B owed for `amount` token A at the feed's current price, rounded up in
+    // the maker's favour
+    #[inline]
+    pub fn cost(
+        &self,
+        amount: u64,
+        price_feed: &AccountInfo,
+        now: i64,
+    ) -> Result<u64, ProgramError> {
+        if *price_feed.key != self.oracle {
+            return Err(EscrowError::InvalidPriceFeed.into());
+        }
+        let feed = PriceFeed::get(price_feed)?;
+        if now.saturating_sub(feed.publish_time) > self.max_age {
+            return Err(EscrowError::StalePrice.into());
+        }
+        let price = feed.price as u128;
+        if feed.conf as u128 * 10_000 > price * self.max_conf_bps as u128 {
+            return Err(EscrowError::PriceUncertain.into());
+        }
+
+        let mut price = (price * (10_000 + self.spread_bps as u128)).div_ceil(10_000);
+        if self.min_price != 0 {
+            price = price.max(self.min_price as u128);
+        }
+        if self.max_price != 0 {
+            price = price.min(self.max_price as u128);
+        }
+
+        let scale = 10u128.pow(feed.exponent.unsigned_abs());
+        let value = (amount as u128)
+            .checked_mul(price)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let cost = if feed.exponent >= 0 {
+            value
+                .checked_mul(scale)
+                .ok_or(ProgramError::ArithmeticOverflow)?
+        } else {
+            value.div_ceil(scale)
+        };
+        u64::try_from(cost).map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+}
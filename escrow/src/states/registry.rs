@@ -0,0 +1,131 @@
+use super::utils::check_eq_pda_and_get_bump;
+use crate::error::EscrowError;
+use bytemuck::{Pod, Zeroable};
+use shank::ShankAccount;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction::create_account,
+    sysvar::Sysvar,
+};
+
+pub const REGISTRY_PAGE_LEN: usize = 64;
+
+// Shank only reads literal array lengths, so `escrows` spells it out
+const _: () = assert!(REGISTRY_PAGE_LEN == 64);
+
+// One page of the open escrows for a mint pair, at
+// ["registry", mint_a, mint_b, page]. Entries past `count` are stale.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, ShankAccount)]
+pub struct RegistryPage {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub page: u32,
+    pub count: u32,
+    pub escrows: [Pubkey; 64],
+}
+
+impl RegistryPage {
+    // Registration is optional: the program id stands in for an unused registry slot
+    #[inline]
+    pub fn is_none(registry: &AccountInfo) -> bool {
+        *registry.key == crate::ID
+    }
+
+    #[inline]
+    pub fn open<'a>(
+        page: u32,
+        payer: &AccountInfo<'a>,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+        registry: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let page_bytes = page.to_le_bytes();
+        let bump = check_eq_pda_and_get_bump(
+            &[b"registry", mint_a.as_ref(), mint_b.as_ref(), &page_bytes],
+            &crate::ID,
+            registry.key,
+        )?;
+        let space = core::mem::size_of::<RegistryPage>();
+        let rent = Rent::get()?.minimum_balance(space);
+
+        invoke_signed(
+            &create_account(payer.key, registry.key, rent, space as u64, &crate::ID),
+            &[payer.clone(), registry.clone()],
+            &[&[
+                b"registry",
+                mint_a.as_ref(),
+                mint_b.as_ref(),
+                &page_bytes,
+                &[bump],
+            ]],
+        )?;
+
+        let mut data = registry.try_borrow_mut_data()?;
+        let registry_data = bytemuck::try_from_bytes_mut::<RegistryPage>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        registry_data.mint_a = *mint_a;
+        registry_data.mint_b = *mint_b;
+        registry_data.page = page;
+        Ok(())
+    }
+
+    // Borrow a page opened by this program for the given mint pair
+    #[inline]
+    fn with_page<R>(
+        registry: &AccountInfo,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+        f: impl FnOnce(&mut RegistryPage) -> Result<R, ProgramError>,
+    ) -> Result<R, ProgramError> {
+        if registry.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let mut data = registry.try_borrow_mut_data()?;
+        let registry_data = bytemuck::try_from_bytes_mut::<RegistryPage>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        if registry_data.mint_a != *mint_a || registry_data.mint_b != *mint_b {
+            return Err(EscrowError::RegistryMismatch.into());
+        }
+        f(registry_data)
+    }
+
+    #[inline]
+    pub fn insert(
+        registry: &AccountInfo,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+        escrow: &Pubkey,
+    ) -> ProgramResult {
+        Self::with_page(registry, mint_a, mint_b, |page| {
+            let count = page.count as usize;
+            if count == REGISTRY_PAGE_LEN {
+                return Err(EscrowError::RegistryFull.into());
+            }
+            page.escrows[count] = *escrow;
+            page.count += 1;
+            Ok(())
+        })
+    }
+
+    // Swap-remove the escrow, so entries don't keep their position
+    #[inline]
+    pub fn remove(
+        registry: &AccountInfo,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+        escrow: &Pubkey,
+    ) -> ProgramResult {
+        Self::with_page(registry, mint_a, mint_b, |page| {
+            let count = page.count as usize;
+            let index = page.escrows[..count]
+                .iter()
+                .position(|entry| entry == escrow)
+                .ok_or(EscrowError::RegistryMismatch)?;
+            page.escrows[index] = page.escrows[count - 1];
+            page.escrows[count - 1] = Pubkey::default();
+            page.count -= 1;
+            Ok(())
+        })
+    }
+}
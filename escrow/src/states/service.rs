@@ -0,0 +1,249 @@
+use super::state::Escrow;
+use super::utils::{
+    check_eq_pda, check_eq_pda_and_get_bump, check_token_program, close_token_account, get_amount,
+    get_owner, is_native, move_lamports, transfer,
+};
+use crate::error::EscrowError;
+use bytemuck::{Pod, Zeroable};
+use shank::ShankAccount;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction::create_account,
+    sysvar::Sysvar,
+};
+
+// Lifecycle of a service escrow. Funds leave the vault when the buyer approves
+// an open or disputed escrow, or when a resolved one is settled.
+pub const SERVICE_OPEN: u8 = 0;
+pub const SERVICE_DISPUTED: u8 = 1;
+pub const SERVICE_RESOLVED: u8 = 2;
+
+// Payment held for a service at ["service", buyer, seed], with its deposit in
+// the usual vault at ["vault", service]. Native SOL is held by the account itself.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankAccount)]
+pub struct Service {
+    pub seed: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    // Third party deciding the split once either side disputes
+    pub arbiter: Pubkey,
+    pub mint: Pubkey,
+    // Deposit held, net of any transfer fee
+    pub amount: u64,
+    // Share of `amount` the arbiter awarded the seller, the rest going back to the buyer
+    pub seller_amount: u64,
+    pub status: u8,
+    #[padding]
+    pub _padding: [u8; 7],
+}
+
+impl Service {
+    #[inline]
+    pub fn get_data_and_bump(service: &AccountInfo) -> Result<(Service, u8), ProgramError> {
+        if service.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let service_data = *bytemuck::try_from_bytes::<Service>(*service.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+
+        let bump = check_eq_pda_and_get_bump(
+            &[
+                b"service",
+                service_data.buyer.as_ref(),
+                service_data.seed.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+            service.key,
+        )?;
+        Ok((service_data, bump))
+    }
+
+    #[inline]
+    fn set(service: &AccountInfo, f: impl FnOnce(&mut Service)) -> ProgramResult {
+        let mut data = service.try_borrow_mut_data()?;
+        let service_data = bytemuck::try_from_bytes_mut::<Service>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        f(service_data);
+        Ok(())
+    }
+
+    // Create the service escrow and take the buyer's deposit into custody
+    #[inline]
+    pub fn init<'a>(
+        seed: u64,
+        amount: u64,
+        seller: Pubkey,
+        arbiter: Pubkey,
+        token_program: &Pubkey,
+        buyer: &AccountInfo<'a>,
+        buyer_ta: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        service: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        // The arbiter has to be neither party, or it could rule for itself
+        if arbiter == Pubkey::default() || arbiter == *buyer.key || arbiter == seller {
+            return Err(EscrowError::InvalidArbiter.into());
+        }
+        if amount == 0 {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+
+        let seed_bytes = seed.to_le_bytes();
+        let bump = check_eq_pda_and_get_bump(
+            &[b"service", buyer.key.as_ref(), &seed_bytes],
+            &crate::ID,
+            service.key,
+        )?;
+        let space = core::mem::size_of::<Service>();
+        let rent = Rent::get()?.minimum_balance(space);
+        invoke_signed(
+            &create_account(buyer.key, service.key, rent, space as u64, &crate::ID),
+            &[buyer.clone(), service.clone()],
+            &[&[b"service", buyer.key.as_ref(), &seed_bytes, &[bump]]],
+        )?;
+
+        let buyer_ta = if is_native(mint.key) {
+            buyer
+        } else {
+            Escrow::create_vault(service.key, token_program, mint, vault, buyer)?;
+            buyer_ta
+        };
+        let amount = Escrow::deposit(
+            service,
+            token_program,
+            amount,
+            buyer_ta,
+            mint,
+            vault,
+            buyer,
+            remaining_accounts,
+        )?;
+
+        Self::set(service, |service_data| {
+            *service_data = Service {
+                seed,
+                buyer: *buyer.key,
+                seller,
+                arbiter,
+                mint: *mint.key,
+                amount,
+                seller_amount: 0,
+                status: SERVICE_OPEN,
+                _padding: [0; 7],
+            }
+        })
+    }
+
+    // Either party can escalate an open escrow to the arbiter
+    #[inline]
+    pub fn dispute(
+        service_data: Service,
+        party: &AccountInfo,
+        service: &AccountInfo,
+    ) -> ProgramResult {
+        if !party.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *party.key != service_data.buyer && *party.key != service_data.seller {
+            return Err(EscrowError::InvalidServiceParty.into());
+        }
+        if service_data.status != SERVICE_OPEN {
+            return Err(EscrowError::InvalidServiceStatus.into());
+        }
+        Self::set(service, |service_data| {
+            service_data.status = SERVICE_DISPUTED
+        })
+    }
+
+    // Record the arbiter's split of a disputed escrow, paid out by `settle`
+    #[inline]
+    pub fn resolve(
+        service_data: Service,
+        seller_amount: u64,
+        arbiter: &AccountInfo,
+        service: &AccountInfo,
+    ) -> ProgramResult {
+        if !arbiter.is_signer || *arbiter.key != service_data.arbiter {
+            return Err(EscrowError::InvalidArbiter.into());
+        }
+        if service_data.status != SERVICE_DISPUTED {
+            return Err(EscrowError::InvalidServiceStatus.into());
+        }
+        if seller_amount > service_data.amount {
+            return Err(EscrowError::InvalidSettlement.into());
+        }
+        Self::set(service, |service_data| {
+            service_data.seller_amount = seller_amount;
+            service_data.status = SERVICE_RESOLVED;
+        })
+    }
+
+    // Pay `seller_amount` to the seller and the rest of the deposit back to the
+    // buyer, then close the vault and the escrow, returning rent to the buyer
+    #[inline]
+    pub fn payout<'a>(
+        service_data: Service,
+        bump: u8,
+        seller_amount: u64,
+        token_program: &Pubkey,
+        buyer: &AccountInfo<'a>,
+        seller: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        buyer_ta: &AccountInfo<'a>,
+        seller_ta: &AccountInfo<'a>,
+        service: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if *buyer.key != service_data.buyer || *seller.key != service_data.seller {
+            return Err(EscrowError::InvalidServiceParty.into());
+        }
+        if *mint.key != service_data.mint {
+            return Err(EscrowError::MintMismatch.into());
+        }
+
+        if is_native(mint.key) {
+            move_lamports(service, seller, seller_amount)?;
+        } else {
+            check_eq_pda(&[b"vault", service.key.as_ref()], &crate::ID, vault.key)?;
+            check_token_program(token_program, mint)?;
+            // Settlement can be cranked by anyone, so tokens must go to the parties
+            if get_owner(buyer_ta)? != *buyer.key || get_owner(seller_ta)? != *seller.key {
+                return Err(EscrowError::InvalidSettlement.into());
+            }
+
+            let seed = service_data.seed.to_le_bytes();
+            let signer_seeds: &[&[&[u8]]] = &[&[b"service", buyer.key.as_ref(), &seed, &[bump]]];
+            // The buyer also gets anything beyond the tracked deposit
+            let refund = get_amount(vault)?
+                .checked_sub(seller_amount)
+                .ok_or(EscrowError::InvalidSettlement)?;
+            for (to, amount) in [(seller_ta, seller_amount), (buyer_ta, refund)] {
+                if amount > 0 {
+                    transfer(
+                        token_program,
+                        mint,
+                        vault,
+                        to,
+                        service,
+                        remaining_accounts,
+                        amount,
+                        signer_seeds,
+                    )?;
+                }
+            }
+            close_token_account(token_program, mint, vault, buyer, service, signer_seeds)?;
+        }
+
+        // The buyer's refund of a native deposit leaves with the rent
+        let balance = service.lamports();
+        service.realloc(0, false)?;
+        **service.lamports.borrow_mut() = 0;
+        **buyer.lamports.borrow_mut() += balance;
+        service.assign(&Pubkey::default());
+        Ok(())
+    }
+}
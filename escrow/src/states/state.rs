@@ -1,21 +1,73 @@
-use super::utils::{check_eq_pda, check_eq_pda_and_get_bump};
+use super::config::{Config, FEE_LEG_A};
+use super::price_feed::OraclePricing;
+use super::registry::RegistryPage;
+use super::utils::{
+    add_transfer_fee, check_eq_pda, check_eq_pda_and_get_bump, check_token_program,
+    close_token_account, create_token_account, get_amount, get_delegate, get_mint, get_owner,
+    is_native, move_lamports, sub_transfer_fee, transfer,
+};
+use crate::{
+    context::Migrate,
+    error::EscrowError,
+    events::{Event, RefundEvent, TakeEvent, UpdateEvent},
+};
 use bytemuck::{Pod, Zeroable};
+use core::cell::{Ref, RefMut};
+use shank::ShankAccount;
 use solana_program::{
     account_info::AccountInfo,
+    clock::Clock,
     entrypoint::ProgramResult,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
-    program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction::create_account,
+    system_instruction::{self, create_account},
     sysvar::Sysvar,
 };
-use spl_token::instruction::{close_account, transfer_checked};
+
+// Leading byte of every escrow account, telling it apart from the program's
+// other accounts. Bump the version whenever the layout changes, and teach
+// Migrate to upgrade the previous one.
+// Version 2 stores the escrow's and vault's bumps in the header.
+pub const ESCROW_DISCRIMINATOR: u8 = 1;
+pub const ESCROW_VERSION: u8 = 2;
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankAccount)]
 pub struct Escrow {
+    pub discriminator: u8,
+    pub version: u8,
+    // Bumps of the escrow and vault PDAs, so they needn't be searched for again.
+    // The vault bump is 0 for native SOL, which has no vault.
+    pub bump: u8,
+    pub vault_bump: u8,
+    #[padding]
+    pub _padding: [u8; 4],
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    // Amount of token B still asked for the `remaining` token A
+    pub receive: u64,
+    // Amount of token A left in the vault
+    pub remaining: u64,
+    // Unix timestamp after which the offer can no longer be taken, 0 if it never expires
+    pub expiry: i64,
+    // Only this counterparty can fill the offer, default if anyone can
+    pub taker: Pubkey,
+    // Registry page listing the offer, default if it isn't listed
+    pub registry: Pubkey,
+    // Price feed the ask follows instead of `receive`, if any
+    pub pricing: OraclePricing,
+}
+
+// Escrow layout as first deployed, before the header was added. Make sized
+// accounts for it but never wrote them, so Migrate gets the terms from the
+// maker and the vault instead of reading them back.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct LegacyEscrow {
     pub seed: u64,
     pub maker: Pubkey,
     pub mint_a: Pubkey,
@@ -24,14 +76,74 @@ pub struct Escrow {
 }
 
 impl Escrow {
+    // Owner and size checks shared by the loaders
+    #[inline]
+    fn check_account(escrow: &AccountInfo) -> ProgramResult {
+        if escrow.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if escrow.data_len() == core::mem::size_of::<LegacyEscrow>() {
+            return Err(EscrowError::OutdatedAccount.into());
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn check_header(&self) -> ProgramResult {
+        if self.discriminator != ESCROW_DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if self.version != ESCROW_VERSION {
+            return Err(EscrowError::OutdatedAccount.into());
+        }
+        Ok(())
+    }
+
+    // Zero-copy view of a current escrow's data, borrowed from the account
+    #[inline]
+    pub fn load<'a>(escrow: &'a AccountInfo) -> Result<Ref<'a, Escrow>, ProgramError> {
+        Self::check_account(escrow)?;
+        let escrow_data = Ref::filter_map(escrow.try_borrow_data()?, |data| {
+            bytemuck::try_from_bytes::<Escrow>(data).ok()
+        })
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+        escrow_data.check_header()?;
+        Ok(escrow_data)
+    }
+
+    // Writes through this land in the account itself
+    #[inline]
+    pub fn load_mut<'a>(escrow: &'a AccountInfo) -> Result<RefMut<'a, Escrow>, ProgramError> {
+        Self::check_account(escrow)?;
+        let escrow_data = RefMut::filter_map(escrow.try_borrow_mut_data()?, |data| {
+            bytemuck::try_from_bytes_mut::<Escrow>(data).ok()
+        })
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+        escrow_data.check_header()?;
+        Ok(escrow_data)
+    }
+
+    // Mutable view of a freshly created escrow, whose header is still zeroed
+    #[inline]
+    fn load_init<'a>(escrow: &'a AccountInfo) -> Result<RefMut<'a, Escrow>, ProgramError> {
+        Self::check_account(escrow)?;
+        let escrow_data = RefMut::filter_map(escrow.try_borrow_mut_data()?, |data| {
+            bytemuck::try_from_bytes_mut::<Escrow>(data).ok()
+        })
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+        if escrow_data.discriminator != 0 {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        Ok(escrow_data)
+    }
+
     #[inline]
     pub fn get_data_and_bump(
         maker: &Pubkey,
         escrow: &AccountInfo,
     ) -> Result<(Escrow, u8), ProgramError> {
         //Get escrow data
-        let escrow_data: Escrow = *bytemuck::try_from_bytes::<Escrow>(*escrow.data.borrow())
-            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let escrow_data = *Self::load(escrow)?;
 
         //check PDA and return bump
 
@@ -47,15 +159,69 @@ impl Escrow {
         Ok((escrow_data, bump))
     }
 
+    // Token B owed for a fill of `amount` token A, pro-rata to the outstanding ask.
+    // Rounds up in the maker's favour; filling the whole remainder pays exactly `receive`.
+    // A partial fill must leave a non-zero ask, otherwise the rest would be free.
+    #[inline]
+    pub fn fill_cost(&self, amount: u64) -> Result<u64, ProgramError> {
+        if amount == 0 || amount > self.remaining {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+        if amount == self.remaining {
+            return Ok(self.receive);
+        }
+        let cost = (amount as u128 * self.receive as u128).div_ceil(self.remaining as u128) as u64;
+        if cost == self.receive {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+        Ok(cost)
+    }
+
+    // Token B owed for a fill of `amount` token A, from the price feed for
+    // oracle-priced offers and from the fixed ask otherwise
+    #[inline]
+    pub fn quote(
+        &self,
+        amount: u64,
+        price_feed: &AccountInfo,
+        now: i64,
+    ) -> Result<u64, ProgramError> {
+        if self.pricing.is_fixed() {
+            return self.fill_cost(amount);
+        }
+        if amount == 0 || amount > self.remaining {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+        match self.pricing.cost(amount, price_feed, now)? {
+            0 => Err(EscrowError::InvalidFillAmount.into()),
+            cost => Ok(cost),
+        }
+    }
+
+    #[inline]
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry != 0 && now >= self.expiry
+    }
+
     #[inline]
     pub fn init<'a>(
         seed: u64,
+        amount: u64,
         receive: u64,
+        expiry: i64,
+        taker: Pubkey,
         mint_a: Pubkey,
         mint_b: Pubkey,
+        registry: Pubkey,
+        pricing: OraclePricing,
+        vault_bump: u8,
         maker: &AccountInfo<'a>,
         escrow: &AccountInfo<'a>,
     ) -> ProgramResult {
+        pricing.check()?;
+        if pricing.is_fixed() && receive == 0 {
+            return Err(EscrowError::InvalidReceive.into());
+        }
         let bump = check_eq_pda_and_get_bump(
             &[b"escrow", maker.key.as_ref(), seed.to_le_bytes().as_ref()],
             &crate::ID,
@@ -79,153 +245,464 @@ impl Escrow {
         )?;
         escrow.assign(&crate::ID);
         // Create the escrow
-        let mut escrow_data: Escrow =
-            *bytemuck::try_from_bytes_mut::<Escrow>(*escrow.data.borrow_mut())
-                .map_err(|_| ProgramError::InvalidAccountData)?;
-        escrow_data.clone_from(&Escrow {
+        *Self::load_init(escrow)? = Escrow {
+            discriminator: ESCROW_DISCRIMINATOR,
+            version: ESCROW_VERSION,
+            bump,
+            vault_bump,
+            _padding: [0; 4],
             seed,
             maker: *maker.key,
             mint_a,
             mint_b,
             receive,
-        });
+            remaining: amount,
+            expiry,
+            taker,
+            registry,
+            pricing,
+        };
 
         Ok(())
     }
 
+    // Create the vault token account, owned by the escrow PDA and paid for by the
+    // maker. Returns the vault's bump.
     #[inline]
-    pub fn deposit<'a>(
+    pub fn create_vault<'a>(
         escrow_address: &Pubkey,
         token_program: &Pubkey,
+        mint_a: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+    ) -> Result<u8, ProgramError> {
+        let bump =
+            check_eq_pda_and_get_bump(&[b"vault", escrow_address.as_ref()], &crate::ID, vault.key)?;
+        create_token_account(
+            maker,
+            vault,
+            mint_a,
+            escrow_address,
+            token_program,
+            &[&[b"vault", escrow_address.as_ref(), &[bump]]],
+        )?;
+        Ok(bump)
+    }
+
+    // Returns the amount that landed in custody, net of any transfer fee.
+    // Native SOL is held by the escrow account itself, so it must already exist.
+    #[inline]
+    pub fn deposit<'a>(
+        escrow: &AccountInfo<'a>,
+        token_program: &Pubkey,
         amount: u64,
         maker_ta_a: &AccountInfo<'a>,
         mint_a: &AccountInfo<'a>,
         vault: &AccountInfo<'a>,
         maker: &AccountInfo<'a>,
-    ) -> ProgramResult {
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> Result<u64, ProgramError> {
+        if is_native(mint_a.key) {
+            invoke(
+                &system_instruction::transfer(maker.key, escrow.key, amount),
+                &[maker.clone(), escrow.clone()],
+            )?;
+            return Ok(amount);
+        }
+
         //check PDA of vault
-        check_eq_pda_and_get_bump(&[b"vault", escrow_address.as_ref()], &crate::ID, vault.key)?;
-        assert_eq!(
-            *escrow_address,
-            *<spl_token::state::Account as spl_token::state::GenericTokenAccount>::unpack_account_owner(*vault.try_borrow_data()?).ok_or(ProgramError::InvalidAccountData)?
-        );
-
-        let decimals = spl_token::state::Mint::unpack(&mint_a.try_borrow_data()?)?.decimals;
-        let ix = transfer_checked(
+        check_eq_pda_and_get_bump(&[b"vault", escrow.key.as_ref()], &crate::ID, vault.key)?;
+        if *escrow.key != get_owner(vault)? {
+            return Err(EscrowError::InvalidVaultOwner.into());
+        }
+        check_token_program(token_program, mint_a)?;
+
+        let before = get_amount(vault)?;
+        transfer(
             token_program,
-            maker_ta_a.key,
-            mint_a.key,
-            vault.key,
-            maker.key,
-            &[],
+            mint_a,
+            maker_ta_a,
+            vault,
+            maker,
+            remaining_accounts,
             amount,
-            decimals,
+            &[],
         )?;
-        let account_info = vec![
-            maker_ta_a.clone(),
-            mint_a.clone(),
-            vault.clone(),
-            maker.clone(),
-        ];
-        invoke(&ix, &account_info)?;
+        Ok(get_amount(vault)? - before)
+    }
+
+    // Send token A out of custody, signed by the escrow PDA
+    #[inline]
+    pub fn release<'a>(
+        token_program: &Pubkey,
+        mint_a: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        to: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if is_native(mint_a.key) {
+            return move_lamports(escrow, to, amount);
+        }
+        transfer(
+            token_program,
+            mint_a,
+            vault,
+            to,
+            escrow,
+            remaining_accounts,
+            amount,
+            signer_seeds,
+        )
+    }
+
+    // Drop a closing escrow from the registry page listing it, if any
+    #[inline]
+    pub fn deregister(
+        escrow_data: &Escrow,
+        escrow: &AccountInfo,
+        registry: &AccountInfo,
+    ) -> ProgramResult {
+        if escrow_data.registry == Pubkey::default() {
+            return Ok(());
+        }
+        if *registry.key != escrow_data.registry {
+            return Err(EscrowError::RegistryMismatch.into());
+        }
+        RegistryPage::remove(
+            registry,
+            &escrow_data.mint_a,
+            &escrow_data.mint_b,
+            escrow.key,
+        )
+    }
+
+    // Close the vault, if any, and then the escrow, returning all rent to the maker
+    #[inline]
+    pub fn close<'a>(
+        token_program: &Pubkey,
+        mint_a: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        //close the vault
+        if !is_native(mint_a.key) {
+            close_token_account(token_program, mint_a, vault, maker, escrow, signer_seeds)?;
+        }
+
+        // Close the escrow
+        let balance = escrow.lamports();
+        escrow.realloc(0, false)?;
+        **escrow.lamports.borrow_mut() = 0;
+        **maker.lamports.borrow_mut() += balance;
+        escrow.assign(&Pubkey::default());
+
         Ok(())
     }
 
+    // For a native SOL leg the token account slots are unused and lamports move
+    // between the taker, maker and treasury wallets directly.
     #[inline]
     pub fn take<'a>(
         escrow_data: Escrow,
         bump: u8,
-        token_program: &Pubkey,
+        amount: u64,
+        min_amount: u64,
+        max_payment: u64,
+        token_program_a: &Pubkey,
+        token_program_b: &Pubkey,
         mint_a: &AccountInfo<'a>,
         mint_b: &AccountInfo<'a>,
         vault: &AccountInfo<'a>,
         maker: &AccountInfo<'a>,
         taker: &AccountInfo<'a>,
+        delegate: Option<&AccountInfo<'a>>,
         escrow: &AccountInfo<'a>,
         maker_ta_b: &AccountInfo<'a>,
         taker_ta_a: &AccountInfo<'a>,
         taker_ta_b: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        treasury_ta: &AccountInfo<'a>,
+        registry: &AccountInfo<'a>,
+        price_feed: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
     ) -> ProgramResult {
         check_eq_pda(&[b"vault", escrow.key.as_ref()], &crate::ID, vault.key)?;
-        assert_eq!(mint_a.key, &escrow_data.mint_a);
-        assert_eq!(mint_b.key, &escrow_data.mint_b);
+        if mint_a.key != &escrow_data.mint_a || mint_b.key != &escrow_data.mint_b {
+            return Err(EscrowError::MintMismatch.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        if escrow_data.is_expired(now) {
+            return Err(EscrowError::Expired.into());
+        }
+
+        // Private offers can only be filled by the designated taker
+        if delegate.is_none() && !taker.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if escrow_data.taker != Pubkey::default() && escrow_data.taker != *taker.key {
+            return Err(EscrowError::InvalidTaker.into());
+        }
+
+        let native_a = is_native(mint_a.key);
+        let native_b = is_native(mint_b.key);
+        let taker_ta_a = if native_a { taker } else { taker_ta_a };
+        let maker_ta_b = if native_b { maker } else { maker_ta_b };
+        // Token B must reach the maker, whoever fills; the account may just have been created
+        if !native_b && get_owner(maker_ta_b)? != *maker.key {
+            return Err(EscrowError::InvalidMakerTokenAccount.into());
+        }
+
+        let seed = escrow_data.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"escrow", maker.key.as_ref(), &seed, &[bump]]];
 
-        // Get token decimals
-        let decimals_a = spl_token::state::Mint::unpack(&mint_a.try_borrow_data()?)?.decimals;
-        let decimals_b = spl_token::state::Mint::unpack(&mint_b.try_borrow_data()?)?.decimals;
+        // Token B is paid by the taker, or by a delegate they approved on their
+        // token B account. The delegate must sign, so nobody else can spend the
+        // approval on terms the taker didn't choose.
+        let authority = match delegate {
+            None => taker,
+            Some(delegate) => {
+                if !delegate.is_signer {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                // Lamports can't be delegated, and token A must reach the account's owner
+                if native_b
+                    || get_owner(taker_ta_b)? != *taker.key
+                    || (!native_a && get_owner(taker_ta_a)? != *taker.key)
+                    || get_delegate(taker_ta_b)? != Some(*delegate.key)
+                {
+                    return Err(EscrowError::InvalidDelegate.into());
+                }
+                delegate
+            }
+        };
 
-        // Get token amount
-        let amount = spl_token::state::Account::unpack(&vault.try_borrow_data()?)?.amount;
+        // Token B owed for this fill, grossed up so the maker receives it after transfer fees
+        let cost = escrow_data.quote(amount, price_feed, now)?;
+        let payment = if native_b {
+            cost
+        } else {
+            add_transfer_fee(mint_b, cost)?
+        };
+        let filled = amount == escrow_data.remaining;
 
-        assert!([&spl_token::ID, &spl_token_2022::ID].contains(&token_program));
+        // The last fill sweeps the whole vault so it can be closed
+        let amount = if filled && !native_a {
+            get_amount(vault)?
+        } else {
+            amount
+        };
+
+        // Each leg settles through its own mint's token program
+        check_token_program(token_program_a, mint_a)?;
+        check_token_program(token_program_b, mint_b)?;
+
+        // Protocol fee, charged to the taker on the configured leg
+        let config_data = Config::get(config)?;
+        let (fee_a, fee_b) = match config_data {
+            Some(config_data) if config_data.fee_leg == FEE_LEG_A => (config_data.fee(amount), 0),
+            Some(config_data) => (0, config_data.fee(cost)),
+            None => (0, 0),
+        };
+        if let Some(config_data) = config_data.filter(|_| fee_a + fee_b > 0) {
+            // Native SOL fees go straight to the treasury wallet
+            let native_fee = if fee_a > 0 { native_a } else { native_b };
+            let treasury = if native_fee {
+                *treasury_ta.key
+            } else {
+                get_owner(treasury_ta)?
+            };
+            if config_data.treasury != treasury {
+                return Err(EscrowError::InvalidTreasury.into());
+            }
+        }
+
+        // Hold the maker to the terms the taker signed for, counting the token A that
+        // actually lands once mint A's own transfer fee is withheld
+        let received = if native_a {
+            amount - fee_a
+        } else {
+            sub_transfer_fee(mint_a, amount - fee_a)?
+        };
+        if received < min_amount || payment.saturating_add(fee_b) > max_payment {
+            return Err(EscrowError::SlippageExceeded.into());
+        }
 
         //claim token A to taker
-        let ix_1 = transfer_checked(
-            token_program,
-            vault.key,
-            mint_a.key,
-            taker_ta_a.key,
-            escrow.key,
-            &[],
-            amount,
-            decimals_a,
+        Self::release(
+            token_program_a,
+            mint_a,
+            escrow,
+            vault,
+            taker_ta_a,
+            remaining_accounts,
+            amount - fee_a,
+            signer_seeds,
         )?;
 
-        invoke_signed(
-            &ix_1,
-            &[
-                vault.clone(),
-                mint_a.clone(),
-                taker_ta_a.clone(),
-                escrow.clone(),
-            ],
-            &[&[
-                b"escrow",
-                maker.key.as_ref(),
-                escrow_data.seed.to_le_bytes().as_ref(),
-                &[bump],
-            ]],
+        //transfer token B to maker
+        transfer(
+            token_program_b,
+            mint_b,
+            taker_ta_b,
+            maker_ta_b,
+            authority,
+            remaining_accounts,
+            payment,
+            &[],
         )?;
 
-        //transfer token B to maker
+        //collect the protocol fee
+        if fee_a > 0 {
+            Self::release(
+                token_program_a,
+                mint_a,
+                escrow,
+                vault,
+                treasury_ta,
+                remaining_accounts,
+                fee_a,
+                signer_seeds,
+            )?;
+        }
+        if fee_b > 0 {
+            transfer(
+                token_program_b,
+                mint_b,
+                taker_ta_b,
+                treasury_ta,
+                authority,
+                remaining_accounts,
+                fee_b,
+                &[],
+            )?;
+        }
+
+        TakeEvent {
+            escrow: *escrow.key,
+            maker: *maker.key,
+            taker: *taker.key,
+            mint_a: *mint_a.key,
+            mint_b: *mint_b.key,
+            amount,
+            paid: cost,
+            fee_a,
+            fee_b,
+            remaining: if filled {
+                0
+            } else {
+                escrow_data.remaining - amount
+            },
+        }
+        .emit();
+
+        // Partial fill: keep the escrow open with what is left
+        if !filled {
+            let mut escrow_state = Self::load_mut(escrow)?;
+            escrow_state.remaining -= amount;
+            // An oracle-priced ask is quoted afresh on every fill
+            if escrow_data.pricing.is_fixed() {
+                escrow_state.receive -= cost;
+            }
+            return Ok(());
+        }
 
-        invoke(
-            &transfer_checked(
+        Self::deregister(&escrow_data, escrow, registry)?;
+        Self::close(token_program_a, mint_a, escrow, vault, maker, signer_seeds)
+    }
+
+    // Amend an open offer in place: top up or withdraw part of the deposit and
+    // replace the ask, expiry and designated taker
+    #[inline]
+    pub fn update<'a>(
+        escrow_data: Escrow,
+        bump: u8,
+        params: Escrow,
+        deposit: u64,
+        withdraw: u64,
+        token_program: &Pubkey,
+        maker_ta_a: &AccountInfo<'a>,
+        mint_a: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        // Check mints match
+        if mint_a.key != &escrow_data.mint_a {
+            return Err(EscrowError::MintMismatch.into());
+        }
+        // Same rule as Make, a fixed ask can't be dropped to nothing
+        if params.pricing.is_fixed() && params.receive == 0 {
+            return Err(EscrowError::InvalidReceive.into());
+        }
+        let maker_ta_a = if is_native(mint_a.key) {
+            maker
+        } else {
+            maker_ta_a
+        };
+
+        let mut remaining = escrow_data.remaining;
+        if deposit > 0 {
+            remaining += Self::deposit(
+                escrow,
                 token_program,
-                taker_ta_b.key,
-                mint_b.key,
-                maker_ta_b.key,
-                taker.key,
-                &[],
-                escrow_data.receive,
-                decimals_b,
-            )?,
-            &[
-                taker_ta_b.clone(),
-                mint_b.clone(),
-                maker_ta_b.clone(),
-                taker.clone(),
-            ],
-        )?;
+                deposit,
+                maker_ta_a,
+                mint_a,
+                vault,
+                maker,
+                remaining_accounts,
+            )?;
+        }
 
-        //close the vault
-        invoke_signed(
-            &close_account(token_program, vault.key, maker.key, escrow.key, &[])?,
-            &[vault.clone(), maker.clone(), escrow.clone()],
-            &[&[
-                b"escrow",
-                maker.key.as_ref(),
-                escrow_data.seed.to_le_bytes().as_ref(),
-                &[bump],
-            ]],
-        )?;
+        if withdraw > 0 {
+            // Withdrawing everything is a Refund
+            if withdraw >= remaining {
+                return Err(EscrowError::InvalidWithdraw.into());
+            }
+            check_eq_pda(&[b"vault", escrow.key.as_ref()], &crate::ID, vault.key)?;
+            check_token_program(token_program, mint_a)?;
+            Self::release(
+                token_program,
+                mint_a,
+                escrow,
+                vault,
+                maker_ta_a,
+                remaining_accounts,
+                withdraw,
+                &[&[
+                    b"escrow",
+                    maker.key.as_ref(),
+                    escrow_data.seed.to_le_bytes().as_ref(),
+                    &[bump],
+                ]],
+            )?;
+            remaining -= withdraw;
+        }
 
-        // Close the escrow
-        let balance = escrow.lamports();
-        escrow.realloc(0, false)?;
-        **escrow.lamports.borrow_mut() = 0;
-        **maker.lamports.borrow_mut() += balance;
-        escrow.assign(&Pubkey::default());
+        let mut escrow_state = Self::load_mut(escrow)?;
+        escrow_state.remaining = remaining;
+        escrow_state.receive = params.receive;
+        escrow_state.expiry = params.expiry;
+        escrow_state.taker = params.taker;
+
+        UpdateEvent {
+            escrow: *escrow.key,
+            maker: *maker.key,
+            taker: params.taker,
+            deposit,
+            withdraw,
+            remaining,
+            receive: params.receive,
+            expiry: params.expiry,
+        }
+        .emit();
 
         Ok(())
     }
@@ -240,65 +717,151 @@ impl Escrow {
         escrow: &AccountInfo<'a>,
         vault: &AccountInfo<'a>,
         maker: &AccountInfo<'a>,
+        registry: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
     ) -> ProgramResult {
         // Check PDA of vault
         check_eq_pda(&[b"vault", escrow.key.as_ref()], &crate::ID, vault.key)?;
 
         // Check mints match
-        assert_eq!(mint_a.key, &escrow_data.mint_a);
+        if mint_a.key != &escrow_data.mint_a {
+            return Err(EscrowError::MintMismatch.into());
+        }
 
-        // Get token decimals
-        let decimals = spl_token::state::Mint::unpack(&mint_a.try_borrow_data()?)?.decimals;
+        let seed = escrow_data.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"escrow", maker.key.as_ref(), &seed, &[bump]]];
 
-        // Get token amount
-        let amount = spl_token::state::Account::unpack(&vault.try_borrow_data()?)?.amount;
-        // By checking this, we know our token accounts are correct by virtue of Token Program checking them
-        assert!([&spl_token::ID, &spl_token_2022::ID].contains(&token_program));
+        // Native SOL is returned with the escrow's own lamports when it closes
+        let mut amount = escrow_data.remaining;
+        if !is_native(mint_a.key) {
+            // Refunds can be cranked by anyone, so token A must go back to the maker
+            if *maker.key != get_owner(maker_ta_a)? {
+                return Err(EscrowError::InvalidMakerTokenAccount.into());
+            }
 
-        invoke_signed(
-            &transfer_checked(
+            // Get token amount
+            amount = get_amount(vault)?;
+            // By checking this, we know our token accounts are correct by virtue of Token Program checking them
+            check_token_program(token_program, mint_a)?;
+
+            Self::release(
                 token_program,
-                vault.key,
-                mint_a.key,
-                maker_ta_a.key,
-                escrow.key,
-                &[],
+                mint_a,
+                escrow,
+                vault,
+                maker_ta_a,
+                remaining_accounts,
                 amount,
-                decimals,
-            )?,
+                signer_seeds,
+            )?;
+        }
+
+        RefundEvent {
+            escrow: *escrow.key,
+            maker: *maker.key,
+            mint_a: *mint_a.key,
+            amount,
+        }
+        .emit();
+
+        Self::deregister(&escrow_data, escrow, registry)?;
+        Self::close(token_program, mint_a, escrow, vault, maker, signer_seeds)
+    }
+
+    // Upgrade an escrow in the legacy layout, or an earlier version of the
+    // current one, in place, with `payer` covering any extra rent. Only its
+    // maker can restate a legacy escrow's terms; anyone can migrate the rest.
+    #[inline]
+    pub fn migrate<'a>(
+        payer: &AccountInfo<'a>,
+        escrow: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        args: Migrate,
+    ) -> ProgramResult {
+        if escrow.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let escrow_data = if escrow.data_len() == core::mem::size_of::<LegacyEscrow>() {
+            if args.receive == 0 {
+                return Err(EscrowError::InvalidReceive.into());
+            }
+            // Token A and what is left of it come from the vault, whichever
+            // token program holds it
+            check_eq_pda(&[b"vault", escrow.key.as_ref()], &crate::ID, vault.key)?;
+            if vault.owner != &spl_token::ID && vault.owner != &spl_token_2022::ID {
+                return Err(ProgramError::IllegalOwner);
+            }
+            if get_owner(vault)? != *escrow.key {
+                return Err(EscrowError::InvalidVaultOwner.into());
+            }
+            // The escrow PDA check below ties the payer to the maker
+            Escrow {
+                discriminator: ESCROW_DISCRIMINATOR,
+                version: ESCROW_VERSION,
+                bump: 0,
+                vault_bump: 0,
+                _padding: [0; 4],
+                seed: args.seed,
+                maker: *payer.key,
+                mint_a: get_mint(vault)?,
+                mint_b: args.mint_b,
+                receive: args.receive,
+                remaining: get_amount(vault)?,
+                expiry: 0,
+                taker: Pubkey::default(),
+                registry: Pubkey::default(),
+                pricing: OraclePricing::default(),
+            }
+        } else {
+            // Version 1 only lacks the bumps
+            let escrow_data = *bytemuck::try_from_bytes::<Escrow>(*escrow.data.borrow())
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+            if escrow_data.discriminator != ESCROW_DISCRIMINATOR || escrow_data.version != 1 {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Escrow {
+                version: ESCROW_VERSION,
+                ..escrow_data
+            }
+        };
+        let bump = check_eq_pda_and_get_bump(
             &[
-                vault.clone(),
-                mint_a.clone(),
-                maker_ta_a.clone(),
-                escrow.clone(),
-            ],
-            &[&[
                 b"escrow",
-                maker.key.as_ref(),
+                escrow_data.maker.as_ref(),
                 escrow_data.seed.to_le_bytes().as_ref(),
-                &[bump],
-            ]],
+            ],
+            &crate::ID,
+            escrow.key,
         )?;
 
-        //close the vault
-        invoke_signed(
-            &close_account(token_program, vault.key, maker.key, escrow.key, &[])?,
-            &[vault.clone(), maker.clone(), escrow.clone()],
-            &[&[
-                b"escrow",
-                maker.key.as_ref(),
-                escrow_data.seed.to_le_bytes().as_ref(),
-                &[bump],
-            ]],
-        )?;
+        let vault_bump = if is_native(&escrow_data.mint_a) {
+            0
+        } else {
+            Pubkey::find_program_address(&[b"vault", escrow.key.as_ref()], &crate::ID).1
+        };
 
-        // Close the escrow
-        let balance = escrow.lamports();
-        escrow.realloc(0, false)?;
-        **escrow.lamports.borrow_mut() = 0;
-        **maker.lamports.borrow_mut() += balance;
-        escrow.assign(&Pubkey::default());
+        let space = core::mem::size_of::<Escrow>();
+        if escrow.data_len() != space {
+            let rent = Rent::get()?;
+            let top_up = rent
+                .minimum_balance(space)
+                .saturating_sub(rent.minimum_balance(escrow.data_len()));
+            if top_up > 0 {
+                invoke(
+                    &system_instruction::transfer(payer.key, escrow.key, top_up),
+                    &[payer.clone(), escrow.clone()],
+                )?;
+            }
+            escrow.realloc(space, false)?;
+        }
 
+        let mut data = escrow.try_borrow_mut_data()?;
+        *bytemuck::try_from_bytes_mut::<Escrow>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)? = Escrow {
+            bump,
+            vault_bump,
+            ..escrow_data
+        };
         Ok(())
     }
 }
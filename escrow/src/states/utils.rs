@@ -1,4 +1,28 @@
-use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use crate::error::EscrowError;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_associated_token_account_client::instruction::create_associated_token_account_idempotent;
+use spl_token_2022::{
+    extension::{
+        transfer_fee::{
+            instruction::harvest_withheld_tokens_to_mint, TransferFeeAmount, TransferFeeConfig,
+        },
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    generic_token_account::GenericTokenAccount,
+    instruction::{close_account, initialize_account3},
+    onchain::invoke_transfer_checked,
+    state::{Account, Mint},
+};
 
 //directly substitute into the code at the point where function is called
 #[inline]
@@ -7,9 +31,8 @@ pub fn check_eq_pda(
     program_id: &Pubkey,
     address: &Pubkey,
 ) -> Result<(), ProgramError> {
-    let (derived_address, _) = Pubkey::try_find_program_address(seeds, program_id)
-        .ok_or(ProgramError::InvalidAccountData)?;
-    Ok(assert!(derived_address.eq(address)))
+    check_eq_pda_and_get_bump(seeds, program_id, address)?;
+    Ok(())
 }
 
 #[inline]
@@ -18,8 +41,272 @@ pub fn check_eq_pda_and_get_bump(
     program_id: &Pubkey,
     address: &Pubkey,
 ) -> Result<u8, ProgramError> {
-    let (derived_address, bump) = Pubkey::try_find_program_address(seeds, program_id)
-        .ok_or(ProgramError::InvalidAccountData)?;
-    assert!(derived_address.eq(address));
+    let (derived_address, bump) =
+        Pubkey::try_find_program_address(seeds, program_id).ok_or(EscrowError::InvalidPda)?;
+    if derived_address.ne(address) {
+        return Err(EscrowError::InvalidPda.into());
+    }
     Ok(bump)
 }
+
+// The classic native mint stands for a leg settled in lamports rather than tokens
+#[inline]
+pub fn is_native(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID
+}
+
+// Move lamports out of an account owned by this program
+#[inline]
+pub fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+    let from_balance = from
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    let to_balance = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_balance;
+    **to.try_borrow_mut_lamports()? = to_balance;
+    Ok(())
+}
+
+// Move `amount` of `mint` from `from` to `to` on the authority's signature.
+// Native SOL is sent as lamports from the authority wallet to `to`.
+#[inline]
+pub fn transfer<'a>(
+    token_program: &Pubkey,
+    mint: &AccountInfo<'a>,
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    remaining_accounts: &[AccountInfo<'a>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    if is_native(mint.key) {
+        return invoke_signed(
+            &system_instruction::transfer(authority.key, to.key, amount),
+            &[authority.clone(), to.clone()],
+            signer_seeds,
+        );
+    }
+    invoke_transfer_checked(
+        token_program,
+        from.clone(),
+        mint.clone(),
+        to.clone(),
+        authority.clone(),
+        remaining_accounts,
+        amount,
+        get_decimals(mint)?,
+        signer_seeds,
+    )
+}
+
+// The mint must belong to the token program used for its leg
+#[inline]
+pub fn check_token_program(token_program: &Pubkey, mint: &AccountInfo) -> ProgramResult {
+    if ![&spl_token::ID, &spl_token_2022::ID].contains(&token_program) {
+        return Err(EscrowError::UnsupportedTokenProgram.into());
+    }
+    if mint.owner != token_program {
+        return Err(EscrowError::TokenProgramMismatch.into());
+    }
+    Ok(())
+}
+
+// Token helpers below unpack through Token-2022's extension-aware state, which
+// also reads classic SPL Token accounts since they share the base layout.
+
+#[inline]
+pub fn get_decimals(mint: &AccountInfo) -> Result<u8, ProgramError> {
+    Ok(
+        StateWithExtensions::<Mint>::unpack(&mint.try_borrow_data()?)?
+            .base
+            .decimals,
+    )
+}
+
+#[inline]
+pub fn get_amount(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(
+        StateWithExtensions::<Account>::unpack(&token_account.try_borrow_data()?)?
+            .base
+            .amount,
+    )
+}
+
+#[inline]
+pub fn get_mint(token_account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    Ok(
+        StateWithExtensions::<Account>::unpack(&token_account.try_borrow_data()?)?
+            .base
+            .mint,
+    )
+}
+
+// Authority the account's owner has approved to spend from it, if any
+#[inline]
+pub fn get_delegate(token_account: &AccountInfo) -> Result<Option<Pubkey>, ProgramError> {
+    Ok(
+        StateWithExtensions::<Account>::unpack(&token_account.try_borrow_data()?)?
+            .base
+            .delegate
+            .into(),
+    )
+}
+
+#[inline]
+pub fn get_owner(token_account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    Account::unpack_account_owner(&token_account.try_borrow_data()?)
+        .copied()
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+// Size of a token account for `mint`, including the extensions its mint requires
+#[inline]
+pub fn get_account_len(mint: &AccountInfo) -> Result<usize, ProgramError> {
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    let extensions =
+        ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+    ExtensionType::try_calculate_account_len::<Account>(&extensions)
+}
+
+// Create a program-derived token account for `mint` held by `owner`, paid for by `payer`
+#[inline]
+pub fn create_token_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    owner: &Pubkey,
+    token_program: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    check_token_program(token_program, mint)?;
+
+    let space = get_account_len(mint)?;
+    let rent = Rent::get()?.minimum_balance(space);
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            account.key,
+            rent,
+            space as u64,
+            token_program,
+        ),
+        &[payer.clone(), account.clone()],
+        signer_seeds,
+    )?;
+    invoke(
+        &initialize_account3(token_program, account.key, mint.key, owner)?,
+        &[account.clone(), mint.clone()],
+    )
+}
+
+// Close an emptied token account owned by a PDA, sending its rent to `destination`
+#[inline]
+pub fn close_token_account<'a>(
+    token_program: &Pubkey,
+    mint: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    harvest_withheld_fees(token_program, mint, account)?;
+    invoke_signed(
+        &close_account(token_program, account.key, destination.key, owner.key, &[])?,
+        &[account.clone(), destination.clone(), owner.clone()],
+        signer_seeds,
+    )
+}
+
+// Create the wallet's associated token account when the caller hasn't, paid for by `payer`
+#[inline]
+pub fn init_ata_if_needed<'a>(
+    payer: &AccountInfo<'a>,
+    ata: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    if !ata.data_is_empty() || is_native(mint.key) {
+        return Ok(());
+    }
+    invoke(
+        &create_associated_token_account_idempotent(
+            payer.key,
+            wallet.key,
+            mint.key,
+            token_program.key,
+        ),
+        &[
+            payer.clone(),
+            ata.clone(),
+            wallet.clone(),
+            mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+        ],
+    )
+}
+
+// Amount to send so that exactly `amount` arrives after any transfer fee on the mint
+#[inline]
+pub fn add_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+    let fee = transfer_fee_config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    amount
+        .checked_add(fee)
+        .ok_or(ProgramError::ArithmeticOverflow)
+}
+
+// Amount that arrives when `amount` is sent, after any transfer fee on the mint
+#[inline]
+pub fn sub_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+    let fee = transfer_fee_config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    amount
+        .checked_sub(fee)
+        .ok_or(ProgramError::ArithmeticOverflow)
+}
+
+// Token-2022 refuses to close an account holding withheld transfer fees, so
+// sweep them to the mint first. Harvesting is permissionless.
+#[inline]
+pub fn harvest_withheld_fees<'a>(
+    token_program: &Pubkey,
+    mint: &AccountInfo<'a>,
+    token_account: &AccountInfo<'a>,
+) -> ProgramResult {
+    let withheld = {
+        let data = token_account.try_borrow_data()?;
+        let account = StateWithExtensions::<Account>::unpack(&data)?;
+        account
+            .get_extension::<TransferFeeAmount>()
+            .map(|fee_amount| u64::from(fee_amount.withheld_amount))
+            .unwrap_or(0)
+    };
+    if withheld == 0 {
+        return Ok(());
+    }
+    invoke(
+        &harvest_withheld_tokens_to_mint(token_program, mint.key, &[token_account.key])?,
+        &[mint.clone(), token_account.clone()],
+    )
+}
@@ -0,0 +1,371 @@
+use super::state::Escrow;
+use super::utils::{
+    check_eq_pda, check_eq_pda_and_get_bump, check_token_program, close_token_account, get_amount,
+    is_native, move_lamports, transfer,
+};
+use crate::error::EscrowError;
+use bytemuck::{Pod, Zeroable};
+use shank::ShankAccount;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction::create_account,
+    sysvar::Sysvar,
+};
+
+// Tokens vesting to a beneficiary at ["vesting", maker, seed], held in the usual
+// vault at ["vault", vesting]. Native SOL is held by the account itself.
+// Nothing vests before `cliff`; from then on `total` vests linearly from `start`
+// until it is fully vested at `end`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, ShankAccount)]
+pub struct Vesting {
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    // Deposit being vested, net of any transfer fee
+    pub total: u64,
+    // Amount the beneficiary has claimed so far
+    pub claimed: u64,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+    // Whether the maker can take back the unvested portion
+    pub revocable: u8,
+    #[padding]
+    pub _padding: [u8; 7],
+}
+
+impl Vesting {
+    #[inline]
+    pub fn get_data_and_bump(
+        maker: &Pubkey,
+        vesting: &AccountInfo,
+    ) -> Result<(Vesting, u8), ProgramError> {
+        if vesting.owner != &crate::ID {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let vesting_data = *bytemuck::try_from_bytes::<Vesting>(*vesting.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+
+        let bump = check_eq_pda_and_get_bump(
+            &[
+                b"vesting",
+                maker.as_ref(),
+                vesting_data.seed.to_le_bytes().as_ref(),
+            ],
+            &crate::ID,
+            vesting.key,
+        )?;
+        Ok((vesting_data, bump))
+    }
+
+    // Amount of `total` vested at `now`, rounded down
+    #[inline]
+    pub fn vested(&self, now: i64) -> u64 {
+        if now < self.cliff {
+            return 0;
+        }
+        if now >= self.end {
+            return self.total;
+        }
+        let elapsed = (now - self.start) as u128;
+        let duration = (self.end - self.start) as u128;
+        (self.total as u128 * elapsed / duration) as u64
+    }
+
+    #[inline]
+    fn set(vesting: &AccountInfo, f: impl FnOnce(&mut Vesting)) -> ProgramResult {
+        let mut data = vesting.try_borrow_mut_data()?;
+        let vesting_data = bytemuck::try_from_bytes_mut::<Vesting>(&mut data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        f(vesting_data);
+        Ok(())
+    }
+
+    // Create the vesting account and take the maker's deposit into custody
+    #[inline]
+    pub fn init<'a>(
+        seed: u64,
+        amount: u64,
+        beneficiary: Pubkey,
+        start: i64,
+        cliff: i64,
+        end: i64,
+        revocable: bool,
+        token_program: &Pubkey,
+        maker: &AccountInfo<'a>,
+        maker_ta: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        vesting: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if start >= end || cliff < start || cliff > end {
+            return Err(EscrowError::InvalidSchedule.into());
+        }
+        if beneficiary == Pubkey::default() {
+            return Err(EscrowError::InvalidBeneficiary.into());
+        }
+        if amount == 0 {
+            return Err(EscrowError::InvalidFillAmount.into());
+        }
+
+        let seed_bytes = seed.to_le_bytes();
+        let bump = check_eq_pda_and_get_bump(
+            &[b"vesting", maker.key.as_ref(), &seed_bytes],
+            &crate::ID,
+            vesting.key,
+        )?;
+        let space = core::mem::size_of::<Vesting>();
+        let rent = Rent::get()?.minimum_balance(space);
+        invoke_signed(
+            &create_account(maker.key, vesting.key, rent, space as u64, &crate::ID),
+            &[maker.clone(), vesting.clone()],
+            &[&[b"vesting", maker.key.as_ref(), &seed_bytes, &[bump]]],
+        )?;
+
+        let maker_ta = if is_native(mint.key) {
+            maker
+        } else {
+            Escrow::create_vault(vesting.key, token_program, mint, vault, maker)?;
+            maker_ta
+        };
+        let total = Escrow::deposit(
+            vesting,
+            token_program,
+            amount,
+            maker_ta,
+            mint,
+            vault,
+            maker,
+            remaining_accounts,
+        )?;
+
+        Self::set(vesting, |vesting_data| {
+            *vesting_data = Vesting {
+                seed,
+                maker: *maker.key,
+                beneficiary,
+                mint: *mint.key,
+                total,
+                claimed: 0,
+                start,
+                cliff,
+                end,
+                revocable: revocable as u8,
+                _padding: [0; 7],
+            }
+        })
+    }
+
+    // Send tokens out of custody, signed by the vesting PDA
+    #[inline]
+    fn release<'a>(
+        vesting_data: &Vesting,
+        bump: u8,
+        token_program: &Pubkey,
+        mint: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        vesting: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        to: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+        amount: u64,
+    ) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+        if is_native(mint.key) {
+            return move_lamports(vesting, to, amount);
+        }
+        let seed = vesting_data.seed.to_le_bytes();
+        transfer(
+            token_program,
+            mint,
+            vault,
+            to,
+            vesting,
+            remaining_accounts,
+            amount,
+            &[&[b"vesting", maker.key.as_ref(), &seed, &[bump]]],
+        )
+    }
+
+    // Check the accounts a claim or revoke moves tokens through
+    #[inline]
+    fn check_accounts(
+        vesting_data: &Vesting,
+        token_program: &Pubkey,
+        mint: &AccountInfo,
+        vesting: &AccountInfo,
+        vault: &AccountInfo,
+    ) -> ProgramResult {
+        if *mint.key != vesting_data.mint {
+            return Err(EscrowError::MintMismatch.into());
+        }
+        if !is_native(mint.key) {
+            check_eq_pda(&[b"vault", vesting.key.as_ref()], &crate::ID, vault.key)?;
+            check_token_program(token_program, mint)?;
+        }
+        Ok(())
+    }
+
+    // Pay the beneficiary everything vested and not yet claimed, closing the
+    // vesting once all of it has been claimed
+    #[inline]
+    pub fn claim<'a>(
+        vesting_data: Vesting,
+        bump: u8,
+        now: i64,
+        token_program: &Pubkey,
+        beneficiary: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        beneficiary_ta: &AccountInfo<'a>,
+        vesting: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if !beneficiary.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *beneficiary.key != vesting_data.beneficiary {
+            return Err(EscrowError::InvalidBeneficiary.into());
+        }
+        Self::check_accounts(&vesting_data, token_program, mint, vesting, vault)?;
+
+        let vested = vesting_data.vested(now);
+        let claimable = vested - vesting_data.claimed;
+        if claimable == 0 {
+            return Err(EscrowError::NothingVested.into());
+        }
+        let beneficiary_ta = if is_native(mint.key) {
+            beneficiary
+        } else {
+            beneficiary_ta
+        };
+
+        // The last claim sweeps the whole vault so it can be closed
+        let done = vested == vesting_data.total;
+        let amount = if done && !is_native(mint.key) {
+            get_amount(vault)?
+        } else {
+            claimable
+        };
+        Self::release(
+            &vesting_data,
+            bump,
+            token_program,
+            mint,
+            maker,
+            vesting,
+            vault,
+            beneficiary_ta,
+            remaining_accounts,
+            amount,
+        )?;
+
+        if done {
+            return Self::close(
+                &vesting_data,
+                bump,
+                token_program,
+                mint,
+                maker,
+                vesting,
+                vault,
+            );
+        }
+        Self::set(vesting, |vesting_data| vesting_data.claimed = vested)
+    }
+
+    // Return the unvested portion to the maker and freeze the schedule, so the
+    // beneficiary can still claim what had vested
+    #[inline]
+    pub fn revoke<'a>(
+        vesting_data: Vesting,
+        bump: u8,
+        now: i64,
+        token_program: &Pubkey,
+        maker: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        maker_ta: &AccountInfo<'a>,
+        vesting: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        if !maker.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if vesting_data.revocable == 0 {
+            return Err(EscrowError::NotRevocable.into());
+        }
+        Self::check_accounts(&vesting_data, token_program, mint, vesting, vault)?;
+        let maker_ta = if is_native(mint.key) { maker } else { maker_ta };
+
+        let vested = vesting_data.vested(now);
+        Self::release(
+            &vesting_data,
+            bump,
+            token_program,
+            mint,
+            maker,
+            vesting,
+            vault,
+            maker_ta,
+            remaining_accounts,
+            vesting_data.total - vested,
+        )?;
+
+        if vested == vesting_data.claimed {
+            return Self::close(
+                &vesting_data,
+                bump,
+                token_program,
+                mint,
+                maker,
+                vesting,
+                vault,
+            );
+        }
+        Self::set(vesting, |vesting_data| {
+            vesting_data.total = vested;
+            vesting_data.start = now;
+            vesting_data.cliff = now;
+            vesting_data.end = now;
+            vesting_data.revocable = 0;
+        })
+    }
+
+    // Close the vault, if any, and then the vesting, returning all rent to the maker
+    #[inline]
+    fn close<'a>(
+        vesting_data: &Vesting,
+        bump: u8,
+        token_program: &Pubkey,
+        mint: &AccountInfo<'a>,
+        maker: &AccountInfo<'a>,
+        vesting: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        if !is_native(mint.key) {
+            let seed = vesting_data.seed.to_le_bytes();
+            close_token_account(
+                token_program,
+                mint,
+                vault,
+                maker,
+                vesting,
+                &[&[b"vesting", maker.key.as_ref(), &seed, &[bump]]],
+            )?;
+        }
+
+        let balance = vesting.lamports();
+        vesting.realloc(0, false)?;
+        **vesting.lamports.borrow_mut() = 0;
+        **maker.lamports.borrow_mut() += balance;
+        vesting.assign(&Pubkey::default());
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use bytemuck::Pod;
+use bytemuck::Zeroable;
 use core::mem;
 use mollusk_svm::{
-    program::{self, create_program_account_loader_v3, keyed_account_for_system_program},
+    program,
     result::{Check, ProgramResult},
     Mollusk,
 };
@@ -9,13 +9,42 @@ use solana_program::instruction::AccountMeta;
 use solana_sdk::{
     account::{Account, WritableAccount},
     instruction::Instruction,
+    program_error::ProgramError,
     program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
 };
+use spl_pod::optional_keys::OptionalNonZeroPubkey;
+use spl_tlv_account_resolution::state::ExtraAccountMetaList;
 use spl_token::state::AccountState;
+use spl_token_2022::extension::{
+    mint_close_authority::MintCloseAuthority,
+    transfer_fee::{TransferFee, TransferFeeConfig},
+    transfer_hook::TransferHook,
+    BaseStateWithExtensions, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensions,
+    StateWithExtensionsMut,
+};
+use spl_transfer_hook_interface::{
+    get_extra_account_metas_address, instruction::ExecuteInstruction,
+};
+
+use super::{
+    client::{
+        self, find_bundle_address, find_bundle_vault_address, find_registry_address, unpack_bundle,
+        unpack_escrow, unpack_registry_page, BundleLeg, MakeBuilder, RefundBuilder, TakeBuilder,
+    },
+    error::EscrowError,
+    events::{EscrowEvent, Event, RefundEvent, EVENT_VERSION},
+    states::{
+        Bundle, Config, CounterOffer, Escrow, Leg, LegacyEscrow, OraclePricing, PriceFeed,
+        RegistryPage, Service, Vesting, ESCROW_DISCRIMINATOR, ESCROW_VERSION, FEE_LEG_A, FEE_LEG_B,
+        PRICE_FEED_MAGIC, SERVICE_DISPUTED, SERVICE_OPEN,
+    },
+};
 
-use super::states::Escrow;
+// Needs the entrypoint it exercises
+#[cfg(all(feature = "optimized", not(feature = "no-entrypoint")))]
+mod optimized;
 
 #[test]
 fn make() {
@@ -118,51 +147,12 @@ fn make() {
     )
     .unwrap();
 
-    let mut vault_account = Account::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Account::LEN),
-        spl_token::state::Account::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        spl_token::state::Account {
-            mint: mint_a,
-            owner: escrow,
-            amount: 0,
-            delegate: COption::None,
-            state: AccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        vault_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    // Created by the program
+    let vault_account = Account::new(0, 0, &Pubkey::default());
 
     let escrow_account = Account::new(0, 0, &Pubkey::default());
 
-    let ix = Instruction::new_with_bytes(
-        crate::ID,
-        &[
-            &[0x00],
-            &seed.to_le_bytes()[..],
-            &100000u64.to_le_bytes()[..],
-            &100000u64.to_le_bytes()[..],
-        ]
-        .concat(),
-        vec![
-            AccountMeta::new(maker, true),
-            AccountMeta::new_readonly(mint_a, false),
-            AccountMeta::new_readonly(mint_b, false),
-            AccountMeta::new(maker_ta_a, false),
-            AccountMeta::new(escrow, false),
-            AccountMeta::new(vault, false),
-            AccountMeta::new_readonly(token_program, false),
-            AccountMeta::new_readonly(system_program, false),
-        ],
-    );
+    let ix = MakeBuilder::new(maker, mint_a, mint_b, seed, 100000, 100000).instruction();
 
     let tx_accs = vec![
         (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
@@ -173,49 +163,69 @@ fn make() {
         (vault, vault_account),
         (token_program, token_program_account),
         (system_program, system_account),
+        (
+            crate::ID,
+            program::create_program_account_loader_v3(&crate::ID),
+        ),
     ];
 
     let result: mollusk_svm::result::InstructionResult =
         mollusk.process_and_validate_instruction(&ix, &tx_accs, &[Check::success()]);
-    assert!(matches!(result.program_result, ProgramResult::Success))
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The offer is written to the account itself, not to a copy of it
+    let escrow_data = unpack_escrow(&result.get_account(&escrow).unwrap().data).unwrap();
+    assert_eq!(escrow_data.discriminator, ESCROW_DISCRIMINATOR);
+    assert_eq!(escrow_data.seed, seed);
+    assert_eq!(escrow_data.maker, maker);
+    assert_eq!(escrow_data.mint_a, mint_a);
+    assert_eq!(escrow_data.mint_b, mint_b);
+    assert_eq!(escrow_data.receive, 100000);
+    assert_eq!(escrow_data.remaining, 100000);
+    assert_eq!(
+        escrow_data.vault_bump,
+        client::find_vault_address(&escrow).1
+    );
 }
 
 #[test]
-fn refund() {
-    // Add our built program binary
+fn make_listed() {
+    //add built program library
     let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
 
-    // Set our seed
-    let seed: u64 = 1337;
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+
+    let seed: u64 = 1234;
 
-    // Programs
     mollusk.add_program(
         &spl_token::ID,
-        "tests/spl_token",
+        "src/tests/spl_token-3.5.0",
         &mollusk_svm::program::loader_keys::LOADER_V4,
     );
+
     let (token_program, token_program_account) = (
         spl_token::ID,
         program::create_program_account_loader_v3(&spl_token::ID),
     );
-    let (system_program, system_program_account) = program::keyed_account_for_system_program();
 
-    // Accounts
     let maker = Pubkey::new_from_array([0x01; 32]);
     let mint_a = Pubkey::new_from_array([0x02; 32]);
     let mint_b = Pubkey::new_from_array([0x03; 32]);
+
     let maker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
         &maker,
         &mint_a,
         &token_program,
     );
-    let (escrow, escrow_bump) = Pubkey::find_program_address(
+
+    let escrow = Pubkey::find_program_address(
         &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
         &crate::ID,
-    );
+    )
+    .0;
+
     let vault = Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID).0;
 
-    // Fill out our account data
     let mut mint_a_account = Account::new(
         mollusk
             .sysvars
@@ -224,7 +234,8 @@ fn refund() {
         spl_token::state::Mint::LEN,
         &token_program,
     );
-    solana_program::program_pack::Pack::pack(
+
+    Pack::pack(
         spl_token::state::Mint {
             mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
             supply: 100_000_000_000,
@@ -235,7 +246,6 @@ fn refund() {
         mint_a_account.data_as_mut_slice(),
     )
     .unwrap();
-
     let mut mint_b_account = Account::new(
         mollusk
             .sysvars
@@ -264,11 +274,11 @@ fn refund() {
         spl_token::state::Account::LEN,
         &token_program,
     );
-    solana_program::program_pack::Pack::pack(
+    Pack::pack(
         spl_token::state::Account {
             mint: mint_a,
             owner: maker,
-            amount: 1_000_000_000 - 100_000,
+            amount: 1_000_000_000,
             delegate: COption::None,
             state: AccountState::Initialized,
             is_native: COption::None,
@@ -279,131 +289,227 @@ fn refund() {
     )
     .unwrap();
 
-    let mut vault_account = Account::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Account::LEN),
-        spl_token::state::Account::LEN,
-        &token_program,
-    );
-    solana_program::program_pack::Pack::pack(
-        spl_token::state::Account {
-            mint: mint_a,
-            owner: escrow,
-            amount: 100_000,
-            delegate: COption::None,
-            state: AccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        vault_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    // Created by the program
+    let vault_account = Account::new(0, 0, &Pubkey::default());
 
-    let mut escrow_account = Account::new(
+    let escrow_account = Account::new(0, 0, &Pubkey::default());
+
+    // An opened registry page that already lists another offer
+    let registry = find_registry_address(&mint_a, &mint_b, 0).0;
+    let mut registry_data = RegistryPage::zeroed();
+    registry_data.mint_a = mint_a;
+    registry_data.mint_b = mint_b;
+    registry_data.count = 1;
+    registry_data.escrows[0] = Pubkey::new_from_array([0x09; 32]);
+    let mut registry_account = Account::new(
         mollusk
             .sysvars
             .rent
-            .minimum_balance(mem::size_of::<Escrow>()),
-        mem::size_of::<Escrow>(),
+            .minimum_balance(mem::size_of::<RegistryPage>()),
+        mem::size_of::<RegistryPage>(),
         &crate::ID,
     );
-    // escrow_account.set_data_from_slice(bytes_of::<Escrow>(&Escrow {
-    //     seed,
-    //     maker,
-    //     mint_a,
-    //     mint_b,
-    //     receive: 100_000,
-    // }));
+    registry_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&registry_data));
 
-    let escrow_data = Escrow {
-        seed,
-        maker,
-        mint_a,
-        mint_b,
-        receive: 100_000,
-    };
+    let ix = MakeBuilder::new(maker, mint_a, mint_b, seed, 100000, 100000)
+        .registry_page(0)
+        .instruction();
 
-    // Create our instruction
-    let instruction = Instruction::new_with_bytes(
-        crate::ID,
-        &[0x02],
-        vec![
-            AccountMeta::new(maker, true),
-            AccountMeta::new_readonly(mint_a, false),
-            AccountMeta::new(maker_ta_a, false),
-            AccountMeta::new(escrow, false),
-            AccountMeta::new(vault, false),
-            AccountMeta::new_readonly(token_program, false),
-            AccountMeta::new_readonly(system_program, false),
-        ],
-    );
+    let tx_accs = vec![
+        (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (mint_a, mint_a_account),
+        (mint_b, mint_b_account),
+        (maker_ta_a, maker_ta_a_account),
+        (escrow, escrow_account),
+        (vault, vault_account),
+        (token_program, token_program_account),
+        (system_program, system_account),
+        (registry, registry_account),
+    ];
 
-    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
-        &instruction,
-        &vec![
-            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
-            (mint_a, mint_a_account),
-            (maker_ta_a, maker_ta_a_account),
-            (escrow, escrow_account),
-            (vault, vault_account),
-            (token_program, token_program_account),
-            (system_program, system_program_account),
-        ],
-    );
+    let result: mollusk_svm::result::InstructionResult =
+        mollusk.process_and_validate_instruction(&ix, &tx_accs, &[Check::success()]);
     assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The offer is appended to the page and remembers where it is listed
+    let listed = unpack_registry_page(&result.get_account(&registry).unwrap().data).unwrap();
+    assert_eq!(listed, vec![Pubkey::new_from_array([0x09; 32]), escrow]);
+    let escrow_data = unpack_escrow(&result.get_account(&escrow).unwrap().data).unwrap();
+    assert_eq!(escrow_data.registry, registry);
 }
 
 #[test]
-fn take() {
-    // Add our built program binary
+fn make_bundle() {
     let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
 
-    // Set our seed
-    let seed: u64 = 1337;
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
 
-    // Programs
     mollusk.add_program(
         &spl_token::ID,
-        "tests/spl_token",
+        "src/tests/spl_token-3.5.0",
         &mollusk_svm::program::loader_keys::LOADER_V4,
     );
     let (token_program, token_program_account) = (
         spl_token::ID,
         program::create_program_account_loader_v3(&spl_token::ID),
     );
-    let (system_program, system_program_account) = program::keyed_account_for_system_program();
 
-    // Accounts
-    let taker = Pubkey::new_from_array([0x04; 32]);
+    // Two tokens offered for one
     let maker = Pubkey::new_from_array([0x01; 32]);
-    let mint_a = Pubkey::new_from_array([0x02; 32]);
-    let mint_b = Pubkey::new_from_array([0x03; 32]);
-    let taker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
-        &taker,
-        &mint_a,
-        &token_program,
+    let offers = [
+        Pubkey::new_from_array([0x02; 32]),
+        Pubkey::new_from_array([0x03; 32]),
+    ];
+    let ask = Pubkey::new_from_array([0x04; 32]);
+    let seed: u64 = 99;
+
+    let mint_account = || {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN,
+            &token_program,
+        );
+        Pack::pack(
+            spl_token::state::Mint {
+                mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+                supply: 100_000_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+    let token_account = |mint: Pubkey| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner: maker,
+                amount: 1_000_000_000,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let leg = |mint: Pubkey, amount: u64| BundleLeg {
+        mint,
+        token_program,
+        amount,
+    };
+    let ix = client::make_bundle(
+        maker,
+        seed,
+        &[leg(offers[0], 100_000), leg(offers[1], 50_000)],
+        &[leg(ask, 200_000)],
+        0,
+        Pubkey::default(),
     );
-    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
-        &taker,
-        &mint_b,
-        &token_program,
+
+    let bundle = find_bundle_address(&maker, seed).0;
+    let mut tx_accs = vec![
+        (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (bundle, Account::default()),
+        (system_program, system_account),
+    ];
+    for mint in offers {
+        tx_accs.extend([
+            (mint, mint_account()),
+            (
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &maker,
+                    &mint,
+                    &token_program,
+                ),
+                token_account(mint),
+            ),
+            (
+                find_bundle_vault_address(&bundle, &mint).0,
+                Account::default(),
+            ),
+            (token_program, token_program_account.clone()),
+        ]);
+    }
+    tx_accs.extend([
+        (ask, mint_account()),
+        (token_program, token_program_account.clone()),
+    ]);
+
+    let result: mollusk_svm::result::InstructionResult =
+        mollusk.process_and_validate_instruction(&ix, &tx_accs, &[Check::success()]);
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // Each offered token sits in its own vault
+    let bundle_data = unpack_bundle(&result.get_account(&bundle).unwrap().data).unwrap();
+    assert_eq!(bundle_data.offers().len(), 2);
+    assert_eq!(bundle_data.offers()[1].mint, offers[1]);
+    assert_eq!(bundle_data.offers()[1].amount, 50_000);
+    assert_eq!(bundle_data.asks()[0].amount, 200_000);
+    let vault = result
+        .get_account(&find_bundle_vault_address(&bundle, &offers[0]).0)
+        .unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&vault.data)
+            .unwrap()
+            .amount,
+        100_000
     );
-    let maker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
-        &maker,
-        &mint_b,
-        &token_program,
+}
+
+#[test]
+fn make_native() {
+    //add built program library
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+
+    let seed: u64 = 4321;
+
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
     );
+
+    // Offer SOL for token B
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = spl_token::native_mint::ID;
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+
     let escrow = Pubkey::find_program_address(
         &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
         &crate::ID,
     )
     .0;
+
     let vault = Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID).0;
 
-    // Fill out our account data
     let mut mint_a_account = Account::new(
         mollusk
             .sysvars
@@ -412,18 +518,17 @@ fn take() {
         spl_token::state::Mint::LEN,
         &token_program,
     );
-    solana_program::program_pack::Pack::pack(
+    Pack::pack(
         spl_token::state::Mint {
-            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
-            supply: 100_000_000_000,
-            decimals: 6,
+            mint_authority: COption::None,
+            supply: 0,
+            decimals: 9,
             is_initialized: true,
             freeze_authority: COption::None,
         },
         mint_a_account.data_as_mut_slice(),
     )
     .unwrap();
-
     let mut mint_b_account = Account::new(
         mollusk
             .sysvars
@@ -432,7 +537,7 @@ fn take() {
         spl_token::state::Mint::LEN,
         &token_program,
     );
-    solana_program::program_pack::Pack::pack(
+    Pack::pack(
         spl_token::state::Mint {
             mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
             supply: 100_000_000_000,
@@ -444,30 +549,116 @@ fn take() {
     )
     .unwrap();
 
-    let mut taker_ta_a_account = Account::new(
+    let ix = MakeBuilder::new(maker, mint_a, mint_b, seed, 500_000_000, 100000).instruction();
+
+    let tx_accs = vec![
+        (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (mint_a, mint_a_account),
+        (mint_b, mint_b_account),
+        (escrow, Account::new(0, 0, &Pubkey::default())),
+        (vault, Account::default()),
+        (token_program, token_program_account),
+        (system_program, system_account),
+        (
+            crate::ID,
+            program::create_program_account_loader_v3(&crate::ID),
+        ),
+    ];
+
+    let result: mollusk_svm::result::InstructionResult =
+        mollusk.process_and_validate_instruction(&ix, &tx_accs, &[Check::success()]);
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The escrow holds its rent plus the offered lamports
+    let escrow_account = result.get_account(&escrow).unwrap();
+    assert_eq!(
+        escrow_account.lamports,
         mollusk
             .sysvars
             .rent
-            .minimum_balance(spl_token::state::Account::LEN),
-        spl_token::state::Account::LEN,
-        &token_program,
+            .minimum_balance(mem::size_of::<Escrow>())
+            + 500_000_000
     );
-    solana_program::program_pack::Pack::pack(
-        spl_token::state::Account {
-            mint: mint_a,
-            owner: taker,
-            amount: 0,
-            delegate: COption::None,
-            state: AccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        taker_ta_a_account.data_as_mut_slice(),
-    )
-    .unwrap();
+}
 
-    let mut taker_ta_b_account = Account::new(
+#[test]
+fn refund() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+
+    // Accounts
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let maker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_a,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_a_account = Account::new(
         mollusk
             .sysvars
             .rent
@@ -477,20 +668,20 @@ fn take() {
     );
     solana_program::program_pack::Pack::pack(
         spl_token::state::Account {
-            mint: mint_b,
-            owner: taker,
-            amount: 1_000_000_000,
+            mint: mint_a,
+            owner: maker,
+            amount: 1_000_000_000 - 100_000,
             delegate: COption::None,
             state: AccountState::Initialized,
             is_native: COption::None,
             delegated_amount: 0,
             close_authority: COption::None,
         },
-        taker_ta_b_account.data_as_mut_slice(),
+        maker_ta_a_account.data_as_mut_slice(),
     )
     .unwrap();
 
-    let mut maker_ta_b_account = Account::new(
+    let mut vault_account = Account::new(
         mollusk
             .sysvars
             .rent
@@ -500,16 +691,167 @@ fn take() {
     );
     solana_program::program_pack::Pack::pack(
         spl_token::state::Account {
-            mint: mint_b,
-            owner: taker,
-            amount: 0,
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
             delegate: COption::None,
             state: AccountState::Initialized,
             is_native: COption::None,
             delegated_amount: 0,
             close_authority: COption::None,
         },
-        maker_ta_b_account.data_as_mut_slice(),
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Create our instruction
+    let instruction = RefundBuilder::new(escrow_data).instruction();
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (maker_ta_a, maker_ta_a_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn refund_expired() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+
+    // Accounts
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let maker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_a,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: maker,
+            amount: 1_000_000_000 - 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_a_account.data_as_mut_slice(),
     )
     .unwrap();
 
@@ -544,7 +886,7 @@ fn take() {
         mem::size_of::<Escrow>(),
         &crate::ID,
     );
-    // escrow_account.set_data_from_slice(bytes_of::<Escrow>(&Escrow {
+    // escrow_account.data_as_mut_slice().copy_from_slice(bytes_of::<Escrow>(&Escrow {
     //     seed,
     //     maker,
     //     mint_a,
@@ -552,41 +894,4329 @@ fn take() {
     //     receive: 100_000,
     // }));
 
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 1_700_000_000,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Past the deadline, so anyone can crank the refund
+    mollusk.sysvars.clock.unix_timestamp = 1_700_000_001;
+
     // Create our instruction
-    let instruction = Instruction::new_with_bytes(
-        crate::ID,
-        &[0x01],
-        vec![
-            AccountMeta::new(taker, true),
-            AccountMeta::new(maker, false),
-            AccountMeta::new_readonly(mint_a, false),
-            AccountMeta::new_readonly(mint_b, false),
-            AccountMeta::new(taker_ta_a, false),
-            AccountMeta::new(taker_ta_b, false),
-            AccountMeta::new(maker_ta_b, false),
-            AccountMeta::new(escrow, false),
-            AccountMeta::new(vault, false),
-            AccountMeta::new_readonly(token_program, false),
-            AccountMeta::new_readonly(system_program, false),
-        ],
-    );
+    let instruction = RefundBuilder::new(escrow_data).expired_instruction();
 
     let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
         &instruction,
-        &vec![
-            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        &[
             (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
             (mint_a, mint_a_account),
-            (mint_b, mint_b_account),
-            (taker_ta_a, taker_ta_a_account),
-            (taker_ta_b, taker_ta_b_account),
-            (maker_ta_b, maker_ta_b_account),
+            (maker_ta_a, maker_ta_a_account),
             (escrow, escrow_account),
             (vault, vault_account),
             (token_program, token_program_account),
             (system_program, system_program_account),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
         ],
     );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+}
 
-    assert!(matches!(result.program_result, ProgramResult::Success))
+#[test]
+fn update() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+
+    // Accounts
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let maker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_a,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: maker,
+            amount: 1_000_000_000 - 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    // escrow_account.data_as_mut_slice().copy_from_slice(bytes_of::<Escrow>(&Escrow {
+    //     seed,
+    //     maker,
+    //     mint_a,
+    //     mint_b,
+    //     receive: 100_000,
+    // }));
+
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of::<Escrow>(&Escrow {
+            discriminator: ESCROW_DISCRIMINATOR,
+            version: ESCROW_VERSION,
+            bump: escrow_bump,
+            vault_bump,
+            _padding: [0; 4],
+            seed,
+            maker,
+            mint_a,
+            mint_b,
+            receive: 100_000,
+            remaining: 100_000,
+            expiry: 0,
+            taker: Pubkey::default(),
+            registry: Pubkey::default(),
+            pricing: OraclePricing::default(),
+        }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[
+            &[0x06],
+            &300_000u64.to_le_bytes()[..],
+            &50_000u64.to_le_bytes()[..],
+            &0u64.to_le_bytes()[..],
+            &1_700_000_000i64.to_le_bytes()[..],
+            Pubkey::default().as_ref(),
+        ]
+        .concat(),
+        vec![
+            AccountMeta::new(maker, true),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new(maker_ta_a, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (maker_ta_a, maker_ta_a_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // Same PDA, topped up deposit and new terms
+    let escrow_data = unpack_escrow(&result.get_account(&escrow).unwrap().data).unwrap();
+    assert_eq!(escrow_data.remaining, 150_000);
+    assert_eq!(escrow_data.receive, 300_000);
+    assert_eq!(escrow_data.expiry, 1_700_000_000);
+}
+
+#[test]
+fn take() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let taker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_a,
+        &token_program,
+    );
+    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_b,
+        &token_program,
+    );
+    let maker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_b,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+    let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: taker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: taker,
+            amount: 1_000_000_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: maker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Create our instruction
+    let instruction = TakeBuilder::new(taker, escrow_data).instruction();
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (mint_b, mint_b_account),
+            (taker_ta_a, taker_ta_a_account),
+            (taker_ta_b, taker_ta_b_account),
+            (maker_ta_b, maker_ta_b_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+            (associated_token_program, associated_token_program_account),
+            (config, Account::default()),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn take_partial() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let taker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_a,
+        &token_program,
+    );
+    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_b,
+        &token_program,
+    );
+    let maker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_b,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+    let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: taker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: taker,
+            amount: 1_000_000_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: maker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 200_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Create our instruction
+    let instruction = TakeBuilder::new(taker, escrow_data)
+        .amount(40_000)
+        .instruction();
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (mint_b, mint_b_account),
+            (taker_ta_a, taker_ta_a_account),
+            (taker_ta_b, taker_ta_b_account),
+            (maker_ta_b, maker_ta_b_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+            (associated_token_program, associated_token_program_account),
+            (config, Account::default()),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // 40% of the deposit was filled for 40% of the ask; the escrow stays open
+    let escrow_data = unpack_escrow(&result.get_account(&escrow).unwrap().data).unwrap();
+    assert_eq!(escrow_data.remaining, 60_000);
+    assert_eq!(escrow_data.receive, 120_000);
+}
+
+#[test]
+fn take_oracle() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let taker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_a,
+        &token_program,
+    );
+    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_b,
+        &token_program,
+    );
+    let maker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_b,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+    let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+    let price_feed = Pubkey::new_from_array([0x07; 32]);
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: taker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: taker,
+            amount: 1_000_000_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: maker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    // 1.5 token B per token A, published just now
+    let mut price_feed_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<PriceFeed>()),
+        mem::size_of::<PriceFeed>(),
+        &Pubkey::new_from_array([0x08; 32]),
+    );
+    price_feed_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&PriceFeed {
+            magic: PRICE_FEED_MAGIC,
+            price: 15_000,
+            conf: 10,
+            exponent: -4,
+            _padding: [0; 4],
+            publish_time: mollusk.sysvars.clock.unix_timestamp,
+        }));
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 200_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing {
+            oracle: price_feed,
+            spread_bps: 100,
+            max_conf_bps: 50,
+            _padding: [0; 4],
+            max_age: 60,
+            min_price: 0,
+            max_price: 0,
+        },
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Create our instruction
+    let instruction = TakeBuilder::new(taker, escrow_data)
+        .amount(40_000)
+        .instruction();
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (mint_b, mint_b_account),
+            (taker_ta_a, taker_ta_a_account),
+            (taker_ta_b, taker_ta_b_account),
+            (maker_ta_b, maker_ta_b_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+            (associated_token_program, associated_token_program_account),
+            (config, Account::default()),
+            (price_feed, price_feed_account),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // 40_000 token A at 1.5 plus a 1% spread, while the fixed ask is left alone
+    let maker_ta_b_data =
+        spl_token::state::Account::unpack(&result.get_account(&maker_ta_b).unwrap().data).unwrap();
+    assert_eq!(maker_ta_b_data.amount, 60_600);
+    let escrow_data = unpack_escrow(&result.get_account(&escrow).unwrap().data).unwrap();
+    assert_eq!(escrow_data.remaining, 60_000);
+    assert_eq!(escrow_data.receive, 200_000);
+}
+
+#[test]
+fn take_wrong_taker() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let taker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_a,
+        &token_program,
+    );
+    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_b,
+        &token_program,
+    );
+    let maker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_b,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+    let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: taker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: taker,
+            amount: 1_000_000_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: maker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        // Reserved for someone else
+        taker: Pubkey::new_from_array([0x07; 32]),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Create our instruction
+    let instruction = TakeBuilder::new(taker, escrow_data).instruction();
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (mint_b, mint_b_account),
+            (taker_ta_a, taker_ta_a_account),
+            (taker_ta_b, taker_ta_b_account),
+            (maker_ta_b, maker_ta_b_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+            (associated_token_program, associated_token_program_account),
+            (config, Account::default()),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidTaker as u32
+    ));
+}
+
+#[test]
+fn take_wrong_mint() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let taker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_a,
+        &token_program,
+    );
+    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_b,
+        &token_program,
+    );
+    let maker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_b,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &crate::ID,
+    );
+    let (vault, vault_bump) =
+        Pubkey::find_program_address(&[b"vault", escrow.as_ref()], &crate::ID);
+    let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+
+    // Fill out our account data
+    let mut mint_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x06; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_a_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: taker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_a_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut taker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: taker,
+            amount: 1_000_000_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        taker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut maker_ta_b_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_b,
+            owner: maker,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        maker_ta_b_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_a,
+            owner: escrow,
+            amount: 100_000,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        // Asks for a different token than the one being paid
+        mint_b: Pubkey::new_from_array([0x0a; 32]),
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // Create our instruction, paying in the token the taker holds
+    let instruction = TakeBuilder::new(
+        taker,
+        Escrow {
+            mint_b,
+            ..escrow_data
+        },
+    )
+    .instruction();
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_a_account),
+            (mint_b, mint_b_account),
+            (taker_ta_a, taker_ta_a_account),
+            (taker_ta_b, taker_ta_b_account),
+            (maker_ta_b, maker_ta_b_account),
+            (escrow, escrow_account),
+            (vault, vault_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+            (associated_token_program, associated_token_program_account),
+            (config, Account::default()),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::MintMismatch as u32
+    ));
+}
+#[test]
+fn init_config() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+
+    // Accounts
+    let admin = Pubkey::new_from_array([0x08; 32]);
+    let treasury = Pubkey::new_from_array([0x09; 32]);
+    let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+    let program_data = Pubkey::find_program_address(
+        &[crate::ID.as_ref()],
+        &solana_sdk::bpf_loader_upgradeable::ID,
+    )
+    .0;
+
+    // ProgramData { slot: 0, upgrade_authority_address: Some(admin) }
+    let mut program_data_account =
+        Account::new(1_000_000_000, 45, &solana_sdk::bpf_loader_upgradeable::ID);
+    program_data_account.data_as_mut_slice()[..4].copy_from_slice(&3u32.to_le_bytes());
+    program_data_account.data_as_mut_slice()[12] = 1;
+    program_data_account.data_as_mut_slice()[13..45].copy_from_slice(admin.as_ref());
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[&[0x04], &25u16.to_le_bytes()[..], &[FEE_LEG_B]].concat(),
+        vec![
+            AccountMeta::new(admin, true),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(treasury, false),
+            AccountMeta::new_readonly(program_data, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &[
+            (admin, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (config, Account::default()),
+            (treasury, Account::default()),
+            (program_data, program_data_account),
+            (system_program, system_program_account),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    let config_data =
+        bytemuck::pod_read_unaligned::<Config>(&result.get_account(&config).unwrap().data);
+    assert_eq!(config_data.admin, admin);
+    assert_eq!(config_data.treasury, treasury);
+    assert_eq!(config_data.fee_bps, 25);
+}
+
+#[test]
+fn take_fee() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let treasury = Pubkey::new_from_array([0x09; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+
+    let (escrow, escrow_data, escrow_account) = escrow_account(
+        &mollusk,
+        Escrow {
+            seed: 1337,
+            maker,
+            mint_a,
+            mint_b,
+            receive: 40_000,
+            remaining: 100_000,
+            ..Escrow::zeroed()
+        },
+    );
+    let vault = client::find_vault_address(&escrow).0;
+    // A 1% fee, charged on whichever leg the config names
+    let accounts = |fee_leg: u8| {
+        [
+            vec![
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (mint_a, mint_account(&mollusk, &token_program)),
+                (mint_b, mint_account(&mollusk, &token_program)),
+                (
+                    ata(&taker, &mint_a),
+                    token_account(&mollusk, &token_program, mint_a, taker, 0),
+                ),
+                (
+                    ata(&taker, &mint_b),
+                    token_account(&mollusk, &token_program, mint_b, taker, 100_000),
+                ),
+                (
+                    ata(&maker, &mint_b),
+                    token_account(&mollusk, &token_program, mint_b, maker, 0),
+                ),
+                (
+                    ata(&treasury, &mint_a),
+                    token_account(&mollusk, &token_program, mint_a, treasury, 0),
+                ),
+                (
+                    ata(&treasury, &mint_b),
+                    token_account(&mollusk, &token_program, mint_b, treasury, 0),
+                ),
+                (escrow, escrow_account.clone()),
+                (
+                    vault,
+                    token_account(&mollusk, &token_program, mint_a, escrow, 100_000),
+                ),
+                config_account(&mollusk, treasury, 100, fee_leg),
+            ],
+            program_accounts(),
+        ]
+        .concat()
+    };
+    let take = |treasury_ta: Pubkey| {
+        TakeBuilder::new(taker, escrow_data)
+            .treasury_ta(treasury_ta)
+            .instruction()
+    };
+
+    // On the A leg the fee is withheld from the token A the taker receives
+    let result = mollusk.process_instruction(&take(ata(&treasury, &mint_a)), &accounts(FEE_LEG_A));
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_a)), 99_000);
+    assert_eq!(token_amount(&result, &ata(&treasury, &mint_a)), 1_000);
+    assert_eq!(token_amount(&result, &ata(&maker, &mint_b)), 40_000);
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_b)), 60_000);
+
+    // On the B leg the taker pays it on top of the maker's ask
+    let result = mollusk.process_instruction(&take(ata(&treasury, &mint_b)), &accounts(FEE_LEG_B));
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_a)), 100_000);
+    assert_eq!(token_amount(&result, &ata(&maker, &mint_b)), 40_000);
+    assert_eq!(token_amount(&result, &ata(&treasury, &mint_b)), 400);
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_b)), 59_600);
+
+    // The fee can't be sent anywhere but the configured treasury
+    let result = mollusk.process_instruction(&take(ata(&taker, &mint_b)), &accounts(FEE_LEG_B));
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidTreasury as u32
+    ));
+}
+
+#[test]
+fn update_config() {
+    let mollusk = mollusk();
+
+    // The admin `config_account` sets up
+    let admin = Pubkey::new_from_array([0x08; 32]);
+    let new_admin = Pubkey::new_from_array([0x0a; 32]);
+    let treasury = Pubkey::new_from_array([0x09; 32]);
+    let new_treasury = Pubkey::new_from_array([0x0b; 32]);
+    let (config, config_account) = config_account(&mollusk, treasury, 25, FEE_LEG_B);
+
+    let update = |signer: Pubkey| {
+        Instruction::new_with_bytes(
+            crate::ID,
+            &[&[0x05], &50u16.to_le_bytes()[..], &[FEE_LEG_A]].concat(),
+            vec![
+                AccountMeta::new_readonly(signer, true),
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(new_treasury, false),
+                AccountMeta::new_readonly(new_admin, false),
+            ],
+        )
+    };
+    let accounts = |signer: Pubkey| {
+        vec![
+            (signer, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (config, config_account.clone()),
+            (new_treasury, Account::default()),
+            (new_admin, Account::default()),
+        ]
+    };
+
+    let result = mollusk.process_instruction(&update(admin), &accounts(admin));
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let config_data =
+        bytemuck::pod_read_unaligned::<Config>(&result.get_account(&config).unwrap().data);
+    assert_eq!(config_data.admin, new_admin);
+    assert_eq!(config_data.treasury, new_treasury);
+    assert_eq!(config_data.fee_bps, 50);
+    assert_eq!(config_data.fee_leg, FEE_LEG_A);
+
+    // Nobody else can change the fee, even by signing
+    let stranger = Pubkey::new_from_array([0x0c; 32]);
+    let result = mollusk.process_instruction(&update(stranger), &accounts(stranger));
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidAdmin as u32
+    ));
+}
+
+#[test]
+fn take_native() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let treasury = Pubkey::new_from_array([0x09; 32]);
+    let mint = Pubkey::new_from_array([0x03; 32]);
+    let native = spl_token::native_mint::ID;
+    let ata = |wallet: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            &mint,
+            &token_program,
+        )
+    };
+    let lamports = |result: &mollusk_svm::result::InstructionResult, key: &Pubkey| {
+        result.get_account(key).unwrap().lamports
+    };
+    let wallet = || Account::new(1_000_000_000, 0, &Pubkey::default());
+
+    // Native SOL for token B: the deposit sits in the escrow's lamports and a
+    // 1% fee on the A leg goes to the treasury wallet
+    let (escrow, escrow_data, account) = escrow_account(
+        &mollusk,
+        Escrow {
+            seed: 1337,
+            maker,
+            mint_a: native,
+            mint_b: mint,
+            receive: 40_000,
+            remaining: 1_000_000_000,
+            ..Escrow::zeroed()
+        },
+    );
+    let rent = mollusk
+        .sysvars
+        .rent
+        .minimum_balance(mem::size_of::<Escrow>());
+    let accounts = [
+        vec![
+            (maker, wallet()),
+            (taker, wallet()),
+            (treasury, wallet()),
+            (native, mint_account(&mollusk, &token_program)),
+            (mint, mint_account(&mollusk, &token_program)),
+            (
+                ata(&taker),
+                token_account(&mollusk, &token_program, mint, taker, 100_000),
+            ),
+            (
+                ata(&maker),
+                token_account(&mollusk, &token_program, mint, maker, 0),
+            ),
+            (escrow, account),
+            (client::find_vault_address(&escrow).0, Account::default()),
+            config_account(&mollusk, treasury, 100, FEE_LEG_A),
+        ],
+        program_accounts(),
+    ]
+    .concat();
+    let result = mollusk.process_instruction(
+        &TakeBuilder::new(taker, escrow_data)
+            .treasury_ta(treasury)
+            .instruction(),
+        &accounts,
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(lamports(&result, &taker), 1_990_000_000);
+    assert_eq!(lamports(&result, &treasury), 1_010_000_000);
+    assert_eq!(lamports(&result, &maker), 1_000_000_000 + rent);
+    assert_eq!(lamports(&result, &escrow), 0);
+    assert_eq!(token_amount(&result, &ata(&maker)), 40_000);
+    assert_eq!(token_amount(&result, &ata(&taker)), 60_000);
+
+    // Token A for native SOL: the taker pays the maker's wallet and a 1% fee on
+    // the B leg goes to the treasury wallet
+    let (escrow, escrow_data, account) = escrow_account(
+        &mollusk,
+        Escrow {
+            seed: 1337,
+            maker,
+            mint_a: mint,
+            mint_b: native,
+            receive: 40_000,
+            remaining: 100_000,
+            ..Escrow::zeroed()
+        },
+    );
+    let vault = client::find_vault_address(&escrow).0;
+    let accounts = [
+        vec![
+            (maker, wallet()),
+            (taker, wallet()),
+            (treasury, wallet()),
+            (native, mint_account(&mollusk, &token_program)),
+            (mint, mint_account(&mollusk, &token_program)),
+            (
+                ata(&taker),
+                token_account(&mollusk, &token_program, mint, taker, 0),
+            ),
+            (escrow, account),
+            (
+                vault,
+                token_account(&mollusk, &token_program, mint, escrow, 100_000),
+            ),
+            config_account(&mollusk, treasury, 100, FEE_LEG_B),
+        ],
+        program_accounts(),
+    ]
+    .concat();
+    let result = mollusk.process_instruction(
+        &TakeBuilder::new(taker, escrow_data)
+            .treasury_ta(treasury)
+            .instruction(),
+        &accounts,
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&taker)), 100_000);
+    assert_eq!(lamports(&result, &taker), 1_000_000_000 - 40_400);
+    assert_eq!(lamports(&result, &treasury), 1_000_000_400);
+    // The maker also gets back the escrow's and the vault's rent
+    assert_eq!(
+        lamports(&result, &maker),
+        1_000_000_000
+            + 40_000
+            + rent
+            + mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN)
+    );
+}
+
+#[test]
+fn refund_native() {
+    let mollusk = mollusk();
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let native = spl_token::native_mint::ID;
+    let (escrow, escrow_data, escrow_account) = escrow_account(
+        &mollusk,
+        Escrow {
+            seed: 1337,
+            maker,
+            mint_a: native,
+            mint_b: Pubkey::new_from_array([0x03; 32]),
+            receive: 40_000,
+            remaining: 1_000_000_000,
+            ..Escrow::zeroed()
+        },
+    );
+    let escrow_lamports = escrow_account.lamports;
+    let accounts = [
+        vec![
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (native, mint_account(&mollusk, &spl_token::ID)),
+            (escrow, escrow_account),
+            (client::find_vault_address(&escrow).0, Account::default()),
+        ],
+        program_accounts(),
+    ]
+    .concat();
+
+    // The deposit comes back with the escrow's rent as it closes
+    let result =
+        mollusk.process_instruction(&RefundBuilder::new(escrow_data).instruction(), &accounts);
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(
+        result.get_account(&maker).unwrap().lamports,
+        1_000_000_000 + escrow_lamports
+    );
+    let escrow_account = result.get_account(&escrow).unwrap();
+    assert_eq!(escrow_account.lamports, 0);
+    assert!(escrow_account.data.is_empty());
+}
+
+#[test]
+fn decode_event() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let event = RefundEvent {
+        escrow: Pubkey::new_from_array([0x07; 32]),
+        maker: Pubkey::new_from_array([0x01; 32]),
+        mint_a: Pubkey::new_from_array([0x02; 32]),
+        amount: 100_000,
+    };
+
+    // As logged by `sol_log_data`
+    let line = format!(
+        "Program data: {} {}",
+        STANDARD.encode([EVENT_VERSION, RefundEvent::TAG]),
+        STANDARD.encode(bytemuck::bytes_of(&event))
+    );
+    assert_eq!(
+        EscrowEvent::from_log(&line),
+        Some(EscrowEvent::Refund(event))
+    );
+
+    // Unknown versions are rejected rather than misread
+    let line = format!(
+        "Program data: {} {}",
+        STANDARD.encode([EVENT_VERSION + 1, RefundEvent::TAG]),
+        STANDARD.encode(bytemuck::bytes_of(&event))
+    );
+    assert_eq!(EscrowEvent::from_log(&line), None);
+}
+
+#[test]
+fn service_dispute() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    // Accounts
+    let buyer = Pubkey::new_from_array([0x01; 32]);
+    let seller = Pubkey::new_from_array([0x04; 32]);
+    let arbiter = Pubkey::new_from_array([0x07; 32]);
+    let mint = Pubkey::new_from_array([0x02; 32]);
+    let buyer_ta = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &buyer,
+        &mint,
+        &token_program,
+    );
+    let seller_ta = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &seller,
+        &mint,
+        &token_program,
+    );
+    let service = client::find_service_address(&buyer, seed).0;
+    let vault = client::find_vault_address(&service).0;
+
+    // Fill out our account data
+    let mut mint_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let token_account = |owner: Pubkey, amount: u64| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let service_data = Service {
+        seed,
+        buyer,
+        seller,
+        arbiter,
+        mint,
+        amount: 100_000,
+        seller_amount: 0,
+        status: SERVICE_DISPUTED,
+        _padding: [0; 7],
+    };
+    let mut service_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Service>()),
+        mem::size_of::<Service>(),
+        &crate::ID,
+    );
+    service_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&service_data));
+
+    // The arbiter awards the seller 30% and the buyer's refund is paid out by anyone
+    let result = mollusk.process_instruction_chain(
+        &[
+            client::resolve_service(arbiter, service, 30_000),
+            client::settle_service(&service_data, token_program),
+        ],
+        &[
+            (buyer, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (seller, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (arbiter, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint, mint_account),
+            (buyer_ta, token_account(buyer, 0)),
+            (seller_ta, token_account(seller, 0)),
+            (service, service_account),
+            (vault, token_account(service, 100_000)),
+            (token_program, token_program_account),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    assert_eq!(amount(&seller_ta), 30_000);
+    assert_eq!(amount(&buyer_ta), 70_000);
+    assert_eq!(result.get_account(&service).unwrap().lamports, 0);
+}
+
+#[test]
+fn service_resolve_undisputed() {
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    let buyer = Pubkey::new_from_array([0x01; 32]);
+    let arbiter = Pubkey::new_from_array([0x07; 32]);
+    let service = client::find_service_address(&buyer, 1337).0;
+
+    let mut service_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Service>()),
+        mem::size_of::<Service>(),
+        &crate::ID,
+    );
+    service_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&Service {
+            seed: 1337,
+            buyer,
+            seller: Pubkey::new_from_array([0x04; 32]),
+            arbiter,
+            mint: Pubkey::new_from_array([0x02; 32]),
+            amount: 100_000,
+            seller_amount: 0,
+            status: SERVICE_OPEN,
+            _padding: [0; 7],
+        }));
+
+    // The arbiter can only rule once a party has disputed
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &client::resolve_service(arbiter, service, 30_000),
+        &[
+            (arbiter, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (service, service_account),
+        ],
+    );
+
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidServiceStatus as u32
+    ));
+}
+
+#[test]
+fn vesting_claim_and_revoke() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    // Accounts
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let beneficiary = Pubkey::new_from_array([0x04; 32]);
+    let mint = Pubkey::new_from_array([0x02; 32]);
+    let maker_ta = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint,
+        &token_program,
+    );
+    let beneficiary_ta = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &beneficiary,
+        &mint,
+        &token_program,
+    );
+    let vesting = client::find_vesting_address(&maker, seed).0;
+    let vault = client::find_vault_address(&vesting).0;
+
+    // Fill out our account data
+    let mut mint_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let token_account = |owner: Pubkey, amount: u64| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    // Halfway through a 100 second schedule, past its cliff
+    let now = mollusk.sysvars.clock.unix_timestamp;
+    let vesting_data = Vesting {
+        seed,
+        maker,
+        beneficiary,
+        mint,
+        total: 100_000,
+        claimed: 0,
+        start: now - 50,
+        cliff: now - 25,
+        end: now + 50,
+        revocable: 1,
+        _padding: [0; 7],
+    };
+    let mut vesting_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Vesting>()),
+        mem::size_of::<Vesting>(),
+        &crate::ID,
+    );
+    vesting_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&vesting_data));
+
+    // The beneficiary claims the vested half, then the maker takes back the rest
+    let result = mollusk.process_instruction_chain(
+        &[
+            client::claim_vesting(&vesting_data, token_program),
+            client::revoke_vesting(&vesting_data, token_program),
+        ],
+        &[
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (
+                beneficiary,
+                Account::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (mint, mint_account),
+            (maker_ta, token_account(maker, 0)),
+            (beneficiary_ta, token_account(beneficiary, 0)),
+            (vesting, vesting_account),
+            (vault, token_account(vesting, 100_000)),
+            (token_program, token_program_account),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    assert_eq!(amount(&beneficiary_ta), 50_000);
+    assert_eq!(amount(&maker_ta), 50_000);
+    // Nothing was left to claim, so the revoke closed the vesting
+    assert_eq!(result.get_account(&vesting).unwrap().lamports, 0);
+}
+
+#[test]
+fn migrate_legacy_escrow() {
+    let mollusk = mollusk();
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let token_program = spl_token_2022::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, 1337);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+
+    // Make sized escrows for the first layout but never wrote them, and half
+    // of this one's deposit has already been taken
+    assert_eq!(mem::size_of::<LegacyEscrow>(), 112);
+    let escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<LegacyEscrow>()),
+        mem::size_of::<LegacyEscrow>(),
+        &crate::ID,
+    );
+    let accounts = |payer: Pubkey| {
+        vec![
+            (payer, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (escrow, escrow_account.clone()),
+            (
+                vault,
+                token_account(&mollusk, &token_program, mint_a, escrow, 50_000),
+            ),
+            (system_program, system_program_account.clone()),
+        ]
+    };
+
+    // Nobody else can restate the maker's terms
+    let stranger = Pubkey::new_from_array([0x09; 32]);
+    let mut instruction = client::migrate_legacy(maker, 1337, mint_b, 100_000);
+    instruction.accounts[0].pubkey = stranger;
+    let result = mollusk.process_instruction(&instruction, &accounts(stranger));
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidPda as u32
+    ));
+
+    let result = mollusk.process_instruction(
+        &client::migrate_legacy(maker, 1337, mint_b, 100_000),
+        &accounts(maker),
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let escrow_account = result.get_account(&escrow).unwrap();
+    assert_eq!(escrow_account.data.len(), mem::size_of::<Escrow>());
+    assert!(
+        escrow_account.lamports
+            >= mollusk
+                .sysvars
+                .rent
+                .minimum_balance(mem::size_of::<Escrow>())
+    );
+    let escrow_data = unpack_escrow(&escrow_account.data).unwrap();
+    assert_eq!(escrow_data.version, ESCROW_VERSION);
+    assert_eq!(escrow_data.bump, escrow_bump);
+    assert_eq!(escrow_data.vault_bump, vault_bump);
+    assert_eq!(escrow_data.seed, 1337);
+    assert_eq!(escrow_data.maker, maker);
+    assert_eq!(escrow_data.mint_a, mint_a);
+    assert_eq!(escrow_data.mint_b, mint_b);
+    assert_eq!(escrow_data.receive, 100_000);
+    assert_eq!(escrow_data.remaining, 50_000);
+    assert_eq!(escrow_data.expiry, 0);
+    assert!(escrow_data.pricing.is_fixed());
+}
+
+#[test]
+fn counter_offer_accept() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+    let (taker_ta_a, taker_ta_b, maker_ta_b) = (
+        ata(&taker, &mint_a),
+        ata(&taker, &mint_b),
+        ata(&maker, &mint_b),
+    );
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, seed);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+    let counter = client::find_counter_offer_address(&escrow, &taker).0;
+    let counter_vault = client::find_vault_address(&counter).0;
+    let config = client::find_config_address().0;
+
+    // Fill out our account data
+    let mint_account = || {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Mint {
+                mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+                supply: 100_000_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let token_account = |mint: Pubkey, owner: Pubkey, amount: u64| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // The taker bids 30_000 B for 40_000 A instead of the asked 40_000 B
+    let make_counter =
+        client::make_counter_offer(taker, &escrow_data, token_program, 40_000, 30_000);
+    let counter_data = CounterOffer {
+        escrow,
+        taker,
+        mint_b,
+        amount_a: 40_000,
+        amount_b: 30_000,
+    };
+    let accept_counter = client::accept_counter_offer(
+        &escrow_data,
+        &counter_data,
+        token_program,
+        token_program,
+        None,
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[make_counter.clone(), accept_counter],
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_account()),
+            (mint_b, mint_account()),
+            (taker_ta_a, token_account(mint_a, taker, 0)),
+            (taker_ta_b, token_account(mint_b, taker, 1_000_000)),
+            (maker_ta_b, token_account(mint_b, maker, 0)),
+            (escrow, escrow_account.clone()),
+            (vault, token_account(mint_a, escrow, 100_000)),
+            (counter, Account::default()),
+            (counter_vault, Account::default()),
+            (token_program, token_program_account.clone()),
+            (system_program, system_program_account.clone()),
+            (associated_token_program, associated_token_program_account),
+            (config, Account::default()),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ],
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    assert_eq!(amount(&taker_ta_a), 40_000);
+    assert_eq!(amount(&taker_ta_b), 970_000);
+    assert_eq!(amount(&maker_ta_b), 30_000);
+    assert_eq!(amount(&vault), 60_000);
+    assert_eq!(result.get_account(&counter).unwrap().lamports, 0);
+    // The rest of the escrow keeps its original price
+    let escrow_state = unpack_escrow(&result.get_account(&escrow).unwrap().data).unwrap();
+    assert_eq!(escrow_state.remaining, 60_000);
+    assert_eq!(escrow_state.receive, 60_000);
+
+    // Anyone else is refused a withdrawal
+    let stranger = Pubkey::new_from_array([0x09; 32]);
+    let result = mollusk.process_instruction_chain(
+        &[
+            make_counter,
+            client::withdraw_counter_offer(stranger, &counter_data, token_program),
+        ],
+        &[
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (stranger, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_b, mint_account()),
+            (taker_ta_b, token_account(mint_b, taker, 1_000_000)),
+            (escrow, escrow_account),
+            (counter, Account::default()),
+            (counter_vault, Account::default()),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidCounterOffer as u32)
+    );
+}
+
+#[test]
+fn match_crossing() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    // Accounts
+    let maker_x = Pubkey::new_from_array([0x01; 32]);
+    let maker_y = Pubkey::new_from_array([0x06; 32]);
+    let cranker = Pubkey::new_from_array([0x07; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+    let (maker_x_ta_b, maker_y_ta_a, cranker_ta_a) = (
+        ata(&maker_x, &mint_b),
+        ata(&maker_y, &mint_a),
+        ata(&cranker, &mint_a),
+    );
+
+    // Fill out our account data
+    let mint_account = || {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Mint {
+                mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+                supply: 100_000_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let token_account = |mint: Pubkey, owner: Pubkey, amount: u64| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let escrow_account = |maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, amount, receive| {
+        let (escrow, bump) = client::find_escrow_address(&maker, 1337);
+        let (vault, vault_bump) = client::find_vault_address(&escrow);
+        let escrow_data = Escrow {
+            discriminator: ESCROW_DISCRIMINATOR,
+            version: ESCROW_VERSION,
+            bump,
+            vault_bump,
+            _padding: [0; 4],
+            seed: 1337,
+            maker,
+            mint_a,
+            mint_b,
+            receive,
+            remaining: amount,
+            expiry: 0,
+            taker: Pubkey::default(),
+            registry: Pubkey::default(),
+            pricing: OraclePricing::default(),
+        };
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(mem::size_of::<Escrow>()),
+            mem::size_of::<Escrow>(),
+            &crate::ID,
+        );
+        account
+            .data_as_mut_slice()
+            .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+        (escrow, vault, escrow_data, account)
+    };
+
+    // X sells A at 0.5 B each while Y bids up to 2/3 B for each A
+    let (escrow_x, vault_x, escrow_x_data, escrow_x_account) =
+        escrow_account(maker_x, mint_a, mint_b, 100_000, 50_000);
+    let (escrow_y, vault_y, escrow_y_data, escrow_y_account) =
+        escrow_account(maker_y, mint_b, mint_a, 80_000, 120_000);
+
+    let accounts = [
+        (cranker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (maker_x, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (maker_y, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (mint_a, mint_account()),
+        (mint_b, mint_account()),
+        (escrow_x, escrow_x_account),
+        (vault_x, token_account(mint_a, escrow_x, 100_000)),
+        (escrow_y, escrow_y_account),
+        (vault_y, token_account(mint_b, escrow_y, 80_000)),
+        (maker_x_ta_b, token_account(mint_b, maker_x, 0)),
+        (maker_y_ta_a, token_account(mint_a, maker_y, 0)),
+        (cranker_ta_a, token_account(mint_a, cranker, 0)),
+        (client::find_config_address().0, Account::default()),
+        (token_program, token_program_account),
+        (
+            crate::ID,
+            program::create_program_account_loader_v3(&crate::ID),
+        ),
+    ];
+
+    let result = mollusk.process_instruction(
+        &client::match_escrows(
+            cranker,
+            &escrow_x_data,
+            &escrow_y_data,
+            token_program,
+            token_program,
+            true,
+            None,
+        ),
+        &accounts,
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    // X is filled at its ask, Y pays 50_000 B at its own price of 75_000 A
+    assert_eq!(amount(&maker_x_ta_b), 50_000);
+    assert_eq!(amount(&maker_y_ta_a), 75_000);
+    // The 25_000 A left over in X goes to the cranker
+    assert_eq!(amount(&cranker_ta_a), 25_000);
+    assert_eq!(result.get_account(&escrow_x).unwrap().lamports, 0);
+    assert_eq!(amount(&vault_y), 30_000);
+    let escrow_state = unpack_escrow(&result.get_account(&escrow_y).unwrap().data).unwrap();
+    assert_eq!(escrow_state.remaining, 30_000);
+    assert_eq!(escrow_state.receive, 45_000);
+
+    // Escrows of the same mint pair never cross
+    let (escrow_z, vault_z, escrow_z_data, escrow_z_account) =
+        escrow_account(maker_y, mint_a, mint_b, 80_000, 10_000);
+    let result = mollusk.process_instruction(
+        &client::match_escrows(
+            cranker,
+            &escrow_x_data,
+            &escrow_z_data,
+            token_program,
+            token_program,
+            false,
+            None,
+        ),
+        &[
+            accounts.to_vec(),
+            vec![
+                (escrow_z, escrow_z_account),
+                (vault_z, token_account(mint_a, escrow_z, 80_000)),
+                (ata(&maker_y, &mint_b), token_account(mint_b, maker_y, 0)),
+            ],
+        ]
+        .concat(),
+    );
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::PricesDoNotCross as u32)
+    );
+
+    // With a 1% fee on both fills, 1_000 A on X's 100_000 and 750 A on the
+    // 75_000 Y receives come out of the surplus
+    let treasury = Pubkey::new_from_array([0x09; 32]);
+    let treasury_ta_a = ata(&treasury, &mint_a);
+    let instruction = client::match_escrows(
+        cranker,
+        &escrow_x_data,
+        &escrow_y_data,
+        token_program,
+        token_program,
+        true,
+        Some(treasury),
+    );
+    let fee_accounts = [
+        accounts
+            .iter()
+            .filter(|(key, _)| *key != client::find_config_address().0)
+            .cloned()
+            .collect(),
+        vec![
+            config_account(&mollusk, treasury, 100, FEE_LEG_B),
+            (treasury_ta_a, token_account(mint_a, treasury, 0)),
+            (ata(&treasury, &mint_b), token_account(mint_b, treasury, 0)),
+        ],
+    ]
+    .concat();
+    let result = mollusk.process_instruction(&instruction, &fee_accounts);
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    assert_eq!(amount(&maker_x_ta_b), 50_000);
+    assert_eq!(amount(&maker_y_ta_a), 75_000);
+    assert_eq!(amount(&treasury_ta_a), 1_750);
+    assert_eq!(amount(&cranker_ta_a), 23_250);
+
+    // Fees go to the configured treasury only
+    let mut instruction = instruction;
+    instruction.accounts[8].pubkey = cranker_ta_a;
+    let result = mollusk.process_instruction(&instruction, &fee_accounts);
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidTreasury as u32)
+    );
+}
+
+#[test]
+fn take_delegated() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let relayer = Pubkey::new_from_array([0x08; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+    let (taker_ta_a, taker_ta_b, maker_ta_b) = (
+        ata(&taker, &mint_a),
+        ata(&taker, &mint_b),
+        ata(&maker, &mint_b),
+    );
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, seed);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+    let config = client::find_config_address().0;
+
+    // Fill out our account data
+    let mint_account = || {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Mint {
+                mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+                supply: 100_000_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let token_account = |mint: Pubkey, owner: Pubkey, amount: u64, delegate: Option<Pubkey>| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: delegate.into(),
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: if delegate.is_some() { amount } else { 0 },
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    // The relayer fills 40_000 without the taker signing
+    let instruction = TakeBuilder::new(taker, escrow_data)
+        .relayer(relayer)
+        .amount(40_000)
+        .instruction();
+    let accounts = |approved: Option<Pubkey>| {
+        vec![
+            (relayer, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (mint_a, mint_account()),
+            (mint_b, mint_account()),
+            (taker_ta_a, token_account(mint_a, taker, 0, None)),
+            (taker_ta_b, token_account(mint_b, taker, 50_000, approved)),
+            (maker_ta_b, token_account(mint_b, maker, 0, None)),
+            (escrow, escrow_account.clone()),
+            (vault, token_account(mint_a, escrow, 100_000, None)),
+            (token_program, token_program_account.clone()),
+            (system_program, system_program_account.clone()),
+            (
+                associated_token_program,
+                associated_token_program_account.clone(),
+            ),
+            (config, Account::default()),
+            (
+                crate::ID,
+                program::create_program_account_loader_v3(&crate::ID),
+            ),
+        ]
+    };
+
+    // Spending the approval the taker gave the relayer
+    let result = mollusk.process_instruction(&instruction, &accounts(Some(relayer)));
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    assert_eq!(amount(&taker_ta_a), 40_000);
+    assert_eq!(amount(&taker_ta_b), 10_000);
+    assert_eq!(amount(&maker_ta_b), 40_000);
+    assert_eq!(amount(&vault), 60_000);
+
+    // Without an approval to the relayer it can't spend the taker's tokens, and
+    // an approval to the escrow is no longer honoured since nobody signs for it
+    for approved in [None, Some(escrow)] {
+        let result = mollusk.process_instruction(&instruction, &accounts(approved));
+        assert!(
+            matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidDelegate as u32)
+        );
+    }
+
+    // The relayer has to sign for the approval to be spent
+    let mut unsigned = instruction.clone();
+    unsigned.accounts[0].is_signer = false;
+    let result = mollusk.process_instruction(&unsigned, &accounts(Some(relayer)));
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::MissingRequiredSignature)
+    ));
+}
+
+#[test]
+fn take_slippage() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(
+        &spl_token::ID,
+        "src/tests/spl_token-3.5.0",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+    let (token_program, token_program_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+    let (system_program, system_program_account) = program::keyed_account_for_system_program();
+    let (associated_token_program, associated_token_program_account) = (
+        spl_associated_token_account::ID,
+        program::create_program_account_loader_v3(&spl_associated_token_account::ID),
+    );
+
+    // Accounts
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+    let (taker_ta_a, taker_ta_b, maker_ta_b) = (
+        ata(&taker, &mint_a),
+        ata(&taker, &mint_b),
+        ata(&maker, &mint_b),
+    );
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, seed);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+    let config = client::find_config_address().0;
+
+    // Fill out our account data
+    let mint_account = || {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Mint {
+                mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+                supply: 100_000_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let token_account = |mint: Pubkey, owner: Pubkey, amount: u64| {
+        let mut account = Account::new(
+            mollusk
+                .sysvars
+                .rent
+                .minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN,
+            &token_program,
+        );
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account
+    };
+
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        _padding: [0; 4],
+        seed,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        expiry: 0,
+        taker: Pubkey::default(),
+        registry: Pubkey::default(),
+        pricing: OraclePricing::default(),
+    };
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    let accounts = vec![
+        (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+        (mint_a, mint_account()),
+        (mint_b, mint_account()),
+        (taker_ta_a, token_account(mint_a, taker, 0)),
+        (taker_ta_b, token_account(mint_b, taker, 1_000_000)),
+        (maker_ta_b, token_account(mint_b, maker, 0)),
+        (escrow, escrow_account),
+        (vault, token_account(mint_a, escrow, 100_000)),
+        (token_program, token_program_account),
+        (system_program, system_program_account),
+        (associated_token_program, associated_token_program_account),
+        (config, Account::default()),
+        (
+            crate::ID,
+            program::create_program_account_loader_v3(&crate::ID),
+        ),
+    ];
+
+    // The ask is 100_000 B, more than the taker is willing to pay
+    let result = mollusk.process_instruction(
+        &TakeBuilder::new(taker, escrow_data)
+            .limits(100_000, 90_000)
+            .instruction(),
+        &accounts,
+    );
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::SlippageExceeded as u32)
+    );
+
+    // So is asking for more token A than the vault holds
+    let result = mollusk.process_instruction(
+        &TakeBuilder::new(taker, escrow_data)
+            .limits(100_001, 100_000)
+            .instruction(),
+        &accounts,
+    );
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::SlippageExceeded as u32)
+    );
+
+    // A partial fill is held to its own limits: 40_000 A costs 40_000 B
+    let result = mollusk.process_instruction(
+        &TakeBuilder::new(taker, escrow_data)
+            .amount(40_000)
+            .limits(40_000, 39_999)
+            .instruction(),
+        &accounts,
+    );
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::SlippageExceeded as u32)
+    );
+
+    // And so is a relayer spending the taker's approval
+    let relayer = Pubkey::new_from_array([0x08; 32]);
+    let mut approved_ta_b = token_account(mint_b, taker, 1_000_000);
+    let mut state = spl_token::state::Account::unpack(&approved_ta_b.data).unwrap();
+    state.delegate = COption::Some(relayer);
+    state.delegated_amount = 1_000_000;
+    Pack::pack(state, approved_ta_b.data_as_mut_slice()).unwrap();
+    let delegated_accounts = accounts
+        .iter()
+        .map(|(key, account)| {
+            if *key == taker_ta_b {
+                (*key, approved_ta_b.clone())
+            } else {
+                (*key, account.clone())
+            }
+        })
+        .chain([(relayer, Account::new(1_000_000_000, 0, &Pubkey::default()))])
+        .collect::<Vec<_>>();
+    for max_payment in [39_999, 40_000] {
+        let result = mollusk.process_instruction(
+            &TakeBuilder::new(taker, escrow_data)
+                .relayer(relayer)
+                .amount(40_000)
+                .limits(40_000, max_payment)
+                .instruction(),
+            &delegated_accounts,
+        );
+        if max_payment < 40_000 {
+            assert!(
+                matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::SlippageExceeded as u32)
+            );
+        } else {
+            assert!(matches!(result.program_result, ProgramResult::Success));
+        }
+    }
+
+    let result = mollusk.process_instruction(
+        &TakeBuilder::new(taker, escrow_data)
+            .limits(100_000, 100_000)
+            .instruction(),
+        &accounts,
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    let amount = |address: &Pubkey| {
+        spl_token::state::Account::unpack(&result.get_account(address).unwrap().data)
+            .unwrap()
+            .amount
+    };
+    assert_eq!(amount(&taker_ta_a), 100_000);
+    assert_eq!(amount(&maker_ta_b), 100_000);
+}
+
+// Shared fixtures: the escrow program with SPL Token, Token-2022 and the
+// associated token program loaded, and plainly initialized token accounts
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&crate::ID, "../../target/deploy/escrow");
+    for (id, elf) in [
+        (spl_token::ID, "src/tests/spl_token-3.5.0"),
+        (spl_token_2022::ID, "src/tests/spl_token_2022-9.0.0"),
+        (
+            spl_associated_token_account::ID,
+            "src/tests/spl_associated_token_account-7.0.0",
+        ),
+    ] {
+        mollusk.add_program(&id, elf, &mollusk_svm::program::loader_keys::LOADER_V4);
+    }
+    mollusk
+}
+
+// Program accounts every instruction chain below may invoke
+fn program_accounts() -> Vec<(Pubkey, Account)> {
+    let mut accounts: Vec<_> = [
+        crate::ID,
+        spl_token::ID,
+        spl_token_2022::ID,
+        spl_associated_token_account::ID,
+    ]
+    .iter()
+    .map(|id| (*id, program::create_program_account_loader_v3(id)))
+    .collect();
+    accounts.push(program::keyed_account_for_system_program());
+    accounts
+}
+
+// Escrow PDA for `escrow_data`'s maker and seed, with its header and bumps
+// filled in. A native SOL offer keeps its deposit in the escrow's lamports.
+fn escrow_account(mollusk: &Mollusk, escrow_data: Escrow) -> (Pubkey, Escrow, Account) {
+    let (escrow, bump) = client::find_escrow_address(&escrow_data.maker, escrow_data.seed);
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump,
+        vault_bump: client::find_vault_address(&escrow).1,
+        ..escrow_data
+    };
+    let deposit = if escrow_data.mint_a == spl_token::native_mint::ID {
+        escrow_data.remaining
+    } else {
+        0
+    };
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>())
+            + deposit,
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+    (escrow, escrow_data, account)
+}
+
+// Config PDA charging `fee_bps` on `fee_leg` for `treasury`
+fn config_account(
+    mollusk: &Mollusk,
+    treasury: Pubkey,
+    fee_bps: u16,
+    fee_leg: u8,
+) -> (Pubkey, Account) {
+    let (config, bump) = client::find_config_address();
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&Config {
+            admin: Pubkey::new_from_array([0x08; 32]),
+            treasury,
+            fee_bps,
+            fee_leg,
+            bump,
+            _padding: [0; 4],
+        }));
+    (config, account)
+}
+
+fn mint_account(mollusk: &Mollusk, token_program: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        token_program,
+    );
+    Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(
+    mollusk: &Mollusk,
+    token_program: &Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> Account {
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        token_program,
+    );
+    Pack::pack(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+// Balance of a token account of either token program after `result`
+fn token_amount(result: &mollusk_svm::result::InstructionResult, address: &Pubkey) -> u64 {
+    spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(
+        &result.get_account(address).unwrap().data,
+    )
+    .unwrap()
+    .base
+    .amount
+}
+
+#[test]
+fn take_creates_missing_atas() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+    let make = MakeBuilder::new(maker, mint_a, mint_b, 1337, 100_000, 40_000);
+    let escrow = make.escrow();
+    let vault = client::find_vault_address(&escrow).0;
+    let config = client::find_config_address().0;
+
+    // The taker has no token A account yet and the maker no token B account
+    let accounts = |maker_ta_b: Account| {
+        [
+            vec![
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (mint_a, mint_account(&mollusk, &token_program)),
+                (mint_b, mint_account(&mollusk, &token_program)),
+                (
+                    ata(&maker, &mint_a),
+                    token_account(&mollusk, &token_program, mint_a, maker, 100_000),
+                ),
+                (
+                    ata(&taker, &mint_b),
+                    token_account(&mollusk, &token_program, mint_b, taker, 40_000),
+                ),
+                (ata(&taker, &mint_a), Account::default()),
+                (ata(&maker, &mint_b), maker_ta_b),
+                (escrow, Account::default()),
+                (vault, Account::default()),
+                (config, Account::default()),
+            ],
+            program_accounts(),
+        ]
+        .concat()
+    };
+    let escrow_data = Escrow {
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 40_000,
+        remaining: 100_000,
+        ..Escrow::zeroed()
+    };
+    let take = TakeBuilder::new(taker, escrow_data).instruction();
+
+    let result = mollusk.process_instruction_chain(
+        &[make.instruction(), take.clone()],
+        &accounts(Account::default()),
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_a)), 100_000);
+    assert_eq!(token_amount(&result, &ata(&maker, &mint_b)), 40_000);
+    assert_eq!(result.get_account(&escrow).unwrap().lamports, 0);
+
+    // An existing token B account must still belong to the maker
+    let result = mollusk.process_instruction_chain(
+        &[make.instruction(), take],
+        &accounts(token_account(&mollusk, &token_program, mint_b, taker, 0)),
+    );
+    assert!(
+        matches!(result.program_result, ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidMakerTokenAccount as u32)
+    );
+}
+
+// A Token-2022 mint carrying `extensions`, each set up by `init`
+fn mint_2022_account(
+    mollusk: &Mollusk,
+    extensions: &[ExtensionType],
+    init: impl FnOnce(&mut StateWithExtensionsMut<spl_token_2022::state::Mint>),
+) -> Account {
+    let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions)
+        .unwrap();
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(len),
+        len,
+        &spl_token_2022::ID,
+    );
+    let mut state = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    init(&mut state);
+    state.base = spl_token_2022::state::Mint {
+        mint_authority: COption::Some(Pubkey::new_from_array([0x05; 32])),
+        supply: 100_000_000_000,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+    account
+}
+
+// A Token-2022 account with the extensions `mint` requires of its holders
+fn token_2022_account(
+    mollusk: &Mollusk,
+    mint: &Account,
+    mint_key: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> Account {
+    let extensions = ExtensionType::get_required_init_account_extensions(
+        &StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data)
+            .unwrap()
+            .get_extension_types()
+            .unwrap(),
+    );
+    let len =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&extensions)
+            .unwrap();
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(len),
+        len,
+        &spl_token_2022::ID,
+    );
+    let mut state = StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    for extension in extensions {
+        state.init_account_extension_from_type(extension).unwrap();
+    }
+    state.base = spl_token_2022::state::Account {
+        mint: mint_key,
+        owner,
+        amount,
+        delegate: COption::None,
+        state: spl_token_2022::state::AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+    account
+}
+
+#[test]
+fn take_transfer_fee_2022() {
+    let mollusk = mollusk();
+    let token_program = spl_token_2022::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+
+    // Token A only carries an extension the escrow doesn't care about, token B
+    // charges 1% on every transfer
+    let mint_a_account =
+        mint_2022_account(&mollusk, &[ExtensionType::MintCloseAuthority], |state| {
+            state.init_extension::<MintCloseAuthority>(true).unwrap();
+        });
+    let mint_b_account =
+        mint_2022_account(&mollusk, &[ExtensionType::TransferFeeConfig], |state| {
+            let fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: 1_000_000.into(),
+                transfer_fee_basis_points: 100.into(),
+            };
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = fee;
+            config.newer_transfer_fee = fee;
+        });
+
+    let make = MakeBuilder::new(maker, mint_a, mint_b, 1337, 100_000, 40_000)
+        .token_programs(token_program, token_program);
+    let escrow = make.escrow();
+    let escrow_data = Escrow {
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 40_000,
+        remaining: 100_000,
+        ..Escrow::zeroed()
+    };
+    let take = TakeBuilder::new(taker, escrow_data)
+        .token_programs(token_program, token_program)
+        .instruction();
+
+    let result = mollusk.process_instruction_chain(
+        &[make.instruction(), take],
+        &[
+            vec![
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (
+                    ata(&maker, &mint_a),
+                    token_2022_account(&mollusk, &mint_a_account, mint_a, maker, 100_000),
+                ),
+                (
+                    ata(&taker, &mint_a),
+                    token_2022_account(&mollusk, &mint_a_account, mint_a, taker, 0),
+                ),
+                (
+                    ata(&taker, &mint_b),
+                    token_2022_account(&mollusk, &mint_b_account, mint_b, taker, 100_000),
+                ),
+                (
+                    ata(&maker, &mint_b),
+                    token_2022_account(&mollusk, &mint_b_account, mint_b, maker, 0),
+                ),
+                (mint_a, mint_a_account),
+                (mint_b, mint_b_account),
+                (escrow, Account::default()),
+                (client::find_vault_address(&escrow).0, Account::default()),
+                (client::find_config_address().0, Account::default()),
+            ],
+            program_accounts(),
+        ]
+        .concat(),
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_a)), 100_000);
+    // The taker pays the transfer fee on top, so the maker gets the full ask
+    assert_eq!(token_amount(&result, &ata(&maker, &mint_b)), 40_000);
+    assert_eq!(
+        token_amount(&result, &ata(&taker, &mint_b)),
+        100_000 - 40_405
+    );
+    assert_eq!(result.get_account(&escrow).unwrap().lamports, 0);
+}
+
+#[test]
+fn take_slippage_transfer_fee_2022() {
+    let mollusk = mollusk();
+    let token_program = spl_token_2022::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+
+    // Token A charges 1% on every transfer, so the vault holds 99_000 and the
+    // taker is left with 98_010 of it
+    let mint_a_account =
+        mint_2022_account(&mollusk, &[ExtensionType::TransferFeeConfig], |state| {
+            let fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: 1_000_000.into(),
+                transfer_fee_basis_points: 100.into(),
+            };
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = fee;
+            config.newer_transfer_fee = fee;
+        });
+    let mint_b_account =
+        mint_2022_account(&mollusk, &[ExtensionType::MintCloseAuthority], |state| {
+            state.init_extension::<MintCloseAuthority>(true).unwrap();
+        });
+
+    let make = MakeBuilder::new(maker, mint_a, mint_b, 1337, 100_000, 40_000)
+        .token_programs(token_program, token_program);
+    let escrow = make.escrow();
+    let escrow_data = Escrow {
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 40_000,
+        remaining: 99_000,
+        ..Escrow::zeroed()
+    };
+    let take = |min_amount: u64| {
+        TakeBuilder::new(taker, escrow_data)
+            .token_programs(token_program, token_program)
+            .limits(min_amount, u64::MAX)
+            .instruction()
+    };
+    let accounts = [
+        vec![
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (
+                ata(&maker, &mint_a),
+                token_2022_account(&mollusk, &mint_a_account, mint_a, maker, 100_000),
+            ),
+            (
+                ata(&taker, &mint_a),
+                token_2022_account(&mollusk, &mint_a_account, mint_a, taker, 0),
+            ),
+            (
+                ata(&taker, &mint_b),
+                token_2022_account(&mollusk, &mint_b_account, mint_b, taker, 100_000),
+            ),
+            (
+                ata(&maker, &mint_b),
+                token_2022_account(&mollusk, &mint_b_account, mint_b, maker, 0),
+            ),
+            (mint_a, mint_a_account),
+            (mint_b, mint_b_account),
+            (escrow, Account::default()),
+            (client::find_vault_address(&escrow).0, Account::default()),
+            (client::find_config_address().0, Account::default()),
+        ],
+        program_accounts(),
+    ]
+    .concat();
+
+    // The whole vault leaves it, but less than that reaches the taker
+    let result = mollusk.process_instruction_chain(&[make.instruction(), take(99_000)], &accounts);
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::SlippageExceeded as u32
+    ));
+
+    let result = mollusk.process_instruction_chain(&[make.instruction(), take(98_010)], &accounts);
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&taker, &mint_a)), 98_010);
+}
+
+#[test]
+fn take_transfer_hook_2022() {
+    let mut mollusk = mollusk();
+    let token_program = spl_token_2022::ID;
+    let hook_program = Pubkey::new_from_array([0x0a; 32]);
+    mollusk.add_program(
+        &hook_program,
+        "src/tests/spl_transfer_hook_example",
+        &mollusk_svm::program::loader_keys::LOADER_V4,
+    );
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            token_program,
+        )
+    };
+
+    // Token A runs a hook on every transfer; token B is plain SPL Token
+    let mint_a_account = mint_2022_account(&mollusk, &[ExtensionType::TransferHook], |state| {
+        state
+            .init_extension::<TransferHook>(true)
+            .unwrap()
+            .program_id = OptionalNonZeroPubkey::try_from(Some(hook_program)).unwrap();
+    });
+    // The hook asks for no accounts beyond its own validation account
+    let validation = get_extra_account_metas_address(&mint_a, &hook_program);
+    let mut validation_account = Account::new(
+        1_000_000_000,
+        ExtraAccountMetaList::size_of(0).unwrap(),
+        &hook_program,
+    );
+    ExtraAccountMetaList::init::<ExecuteInstruction>(validation_account.data_as_mut_slice(), &[])
+        .unwrap();
+    let hook_accounts = vec![
+        AccountMeta::new_readonly(hook_program, false),
+        AccountMeta::new_readonly(validation, false),
+    ];
+
+    let make = MakeBuilder::new(maker, mint_a, mint_b, 1337, 100_000, 40_000)
+        .token_programs(token_program, spl_token::ID)
+        .remaining_accounts(hook_accounts.clone());
+    let escrow = make.escrow();
+    let escrow_data = Escrow {
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 40_000,
+        remaining: 100_000,
+        ..Escrow::zeroed()
+    };
+    let take = TakeBuilder::new(taker, escrow_data).token_programs(token_program, spl_token::ID);
+
+    let accounts = [
+        vec![
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (
+                ata(&maker, &mint_a, &token_program),
+                token_2022_account(&mollusk, &mint_a_account, mint_a, maker, 100_000),
+            ),
+            (
+                ata(&taker, &mint_a, &token_program),
+                token_2022_account(&mollusk, &mint_a_account, mint_a, taker, 0),
+            ),
+            (
+                ata(&taker, &mint_b, &spl_token::ID),
+                token_account(&mollusk, &spl_token::ID, mint_b, taker, 40_000),
+            ),
+            (
+                ata(&maker, &mint_b, &spl_token::ID),
+                token_account(&mollusk, &spl_token::ID, mint_b, maker, 0),
+            ),
+            (mint_a, mint_a_account),
+            (mint_b, mint_account(&mollusk, &spl_token::ID)),
+            (escrow, Account::default()),
+            (client::find_vault_address(&escrow).0, Account::default()),
+            (client::find_config_address().0, Account::default()),
+            (validation, validation_account),
+            (
+                hook_program,
+                program::create_program_account_loader_v3(&hook_program),
+            ),
+        ],
+        program_accounts(),
+    ]
+    .concat();
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            make.instruction(),
+            take.clone().remaining_accounts(hook_accounts).instruction(),
+        ],
+        &accounts,
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(
+        token_amount(&result, &ata(&taker, &mint_a, &token_program)),
+        100_000
+    );
+    assert_eq!(
+        token_amount(&result, &ata(&maker, &mint_b, &spl_token::ID)),
+        40_000
+    );
+
+    // Token-2022 can't run the hook without its accounts
+    let result =
+        mollusk.process_instruction_chain(&[make.instruction(), take.instruction()], &accounts);
+    assert!(matches!(result.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn take_mixed_token_programs() {
+    let mollusk = mollusk();
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            token_program,
+        )
+    };
+
+    // Token A is classic SPL Token, token B lives under Token-2022
+    let mint_b_account = mint_2022_account(&mollusk, &[], |_| {});
+    let make = MakeBuilder::new(maker, mint_a, mint_b, 1337, 100_000, 40_000)
+        .token_programs(spl_token::ID, spl_token_2022::ID);
+    let escrow = make.escrow();
+    let escrow_data = Escrow {
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 40_000,
+        remaining: 100_000,
+        ..Escrow::zeroed()
+    };
+    let take = TakeBuilder::new(taker, escrow_data);
+
+    let accounts = [
+        vec![
+            (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (
+                ata(&maker, &mint_a, &spl_token::ID),
+                token_account(&mollusk, &spl_token::ID, mint_a, maker, 100_000),
+            ),
+            (
+                ata(&taker, &mint_a, &spl_token::ID),
+                token_account(&mollusk, &spl_token::ID, mint_a, taker, 0),
+            ),
+            (
+                ata(&taker, &mint_b, &spl_token_2022::ID),
+                token_2022_account(&mollusk, &mint_b_account, mint_b, taker, 40_000),
+            ),
+            (
+                ata(&maker, &mint_b, &spl_token_2022::ID),
+                token_2022_account(&mollusk, &mint_b_account, mint_b, maker, 0),
+            ),
+            (mint_a, mint_account(&mollusk, &spl_token::ID)),
+            (mint_b, mint_b_account),
+            (escrow, Account::default()),
+            (client::find_vault_address(&escrow).0, Account::default()),
+            (client::find_config_address().0, Account::default()),
+        ],
+        program_accounts(),
+    ]
+    .concat();
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            make.instruction(),
+            take.clone()
+                .token_programs(spl_token::ID, spl_token_2022::ID)
+                .instruction(),
+        ],
+        &accounts,
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(
+        token_amount(&result, &ata(&taker, &mint_a, &spl_token::ID)),
+        100_000
+    );
+    assert_eq!(
+        token_amount(&result, &ata(&maker, &mint_b, &spl_token_2022::ID)),
+        40_000
+    );
+
+    // Naming SPL Token for the Token-2022 leg is rejected before any transfer
+    let mut accounts = accounts;
+    accounts.extend([
+        (ata(&taker, &mint_b, &spl_token::ID), Account::default()),
+        (ata(&maker, &mint_b, &spl_token::ID), Account::default()),
+    ]);
+    let result = mollusk.process_instruction_chain(
+        &[
+            make.instruction(),
+            take.token_programs(spl_token::ID, spl_token::ID)
+                .instruction(),
+        ],
+        &accounts,
+    );
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::TokenProgramMismatch as u32
+    ));
+}
+
+#[test]
+fn take_bundle_maker_ta() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let offer = Pubkey::new_from_array([0x02; 32]);
+    let ask = Pubkey::new_from_array([0x03; 32]);
+    let seed: u64 = 99;
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+
+    let leg = |mint: Pubkey, amount: u64| BundleLeg {
+        mint,
+        token_program,
+        amount,
+    };
+    let make = client::make_bundle(
+        maker,
+        seed,
+        &[leg(offer, 100_000)],
+        &[leg(ask, 200_000)],
+        0,
+        Pubkey::default(),
+    );
+    let mut bundle_data = Bundle::zeroed();
+    bundle_data.seed = seed;
+    bundle_data.maker = maker;
+    bundle_data.offer_count = 1;
+    bundle_data.ask_count = 1;
+    bundle_data.offers[0] = Leg {
+        mint: offer,
+        amount: 100_000,
+    };
+    bundle_data.asks[0] = Leg {
+        mint: ask,
+        amount: 200_000,
+    };
+    let take = client::take_bundle(
+        taker,
+        &bundle_data,
+        &[token_program],
+        &[token_program],
+        None,
+    );
+
+    let bundle = find_bundle_address(&maker, seed).0;
+    let accounts = |maker_ta_owner: Pubkey| {
+        [
+            vec![
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (bundle, Account::default()),
+                (offer, mint_account(&mollusk, &token_program)),
+                (ask, mint_account(&mollusk, &token_program)),
+                (
+                    ata(&maker, &offer),
+                    token_account(&mollusk, &token_program, offer, maker, 100_000),
+                ),
+                (
+                    find_bundle_vault_address(&bundle, &offer).0,
+                    Account::default(),
+                ),
+                (ata(&taker, &offer), Account::default()),
+                (
+                    ata(&taker, &ask),
+                    token_account(&mollusk, &token_program, ask, taker, 200_000),
+                ),
+                (
+                    ata(&maker, &ask),
+                    token_account(&mollusk, &token_program, ask, maker_ta_owner, 0),
+                ),
+                (client::find_config_address().0, Account::default()),
+            ],
+            program_accounts(),
+        ]
+        .concat()
+    };
+
+    let result = mollusk.process_instruction_chain(&[make.clone(), take.clone()], &accounts(maker));
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    assert_eq!(token_amount(&result, &ata(&maker, &ask)), 200_000);
+    assert_eq!(token_amount(&result, &ata(&taker, &offer)), 100_000);
+
+    // The ask's account already exists but isn't the maker's
+    let result = mollusk.process_instruction_chain(&[make, take], &accounts(taker));
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidMakerTokenAccount as u32
+    ));
+}
+
+#[test]
+fn counter_offer_reject() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let taker_ta_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &taker,
+        &mint_b,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, 1337);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+    let counter = client::find_counter_offer_address(&escrow, &taker).0;
+    let counter_vault = client::find_vault_address(&counter).0;
+
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        ..Escrow::zeroed()
+    };
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    let counter_data = CounterOffer {
+        escrow,
+        taker,
+        mint_b,
+        amount_a: 40_000,
+        amount_b: 30_000,
+    };
+    // The maker turns the bid down rather than letting it sit
+    let result = mollusk.process_instruction_chain(
+        &[
+            client::make_counter_offer(taker, &escrow_data, token_program, 40_000, 30_000),
+            client::withdraw_counter_offer(maker, &counter_data, token_program),
+        ],
+        &[
+            vec![
+                (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (mint_b, mint_account(&mollusk, &token_program)),
+                (
+                    taker_ta_b,
+                    token_account(&mollusk, &token_program, mint_b, taker, 1_000_000),
+                ),
+                (escrow, escrow_account),
+                (
+                    vault,
+                    token_account(&mollusk, &token_program, mint_a, escrow, 100_000),
+                ),
+                (counter, Account::default()),
+                (counter_vault, Account::default()),
+            ],
+            program_accounts(),
+        ]
+        .concat(),
+    );
+
+    assert!(matches!(result.program_result, ProgramResult::Success));
+    // The deposit goes back to the taker and the escrow is untouched
+    assert_eq!(token_amount(&result, &taker_ta_b), 1_000_000);
+    assert_eq!(result.get_account(&counter).unwrap().lamports, 0);
+    assert_eq!(result.get_account(&counter_vault).unwrap().lamports, 0);
+    assert_eq!(token_amount(&result, &vault), 100_000);
+    assert_eq!(
+        unpack_escrow(&result.get_account(&escrow).unwrap().data)
+            .unwrap()
+            .remaining,
+        100_000
+    );
+}
+
+#[test]
+fn counter_offer_accept_wrong_taker_account() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let taker = Pubkey::new_from_array([0x04; 32]);
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let ata = |wallet: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            wallet,
+            mint,
+            &token_program,
+        )
+    };
+    let (maker_ta_a, maker_ta_b, taker_ta_b) = (
+        ata(&maker, &mint_a),
+        ata(&maker, &mint_b),
+        ata(&taker, &mint_b),
+    );
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, 1337);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+    let counter = client::find_counter_offer_address(&escrow, &taker).0;
+    let counter_vault = client::find_vault_address(&counter).0;
+    let config = client::find_config_address().0;
+
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        bump: escrow_bump,
+        vault_bump,
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 100_000,
+        remaining: 100_000,
+        ..Escrow::zeroed()
+    };
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+
+    let counter_data = CounterOffer {
+        escrow,
+        taker,
+        mint_b,
+        amount_a: 40_000,
+        amount_b: 30_000,
+    };
+    // The maker points the taker's token A at their own account
+    let mut accept_counter = client::accept_counter_offer(
+        &escrow_data,
+        &counter_data,
+        token_program,
+        token_program,
+        None,
+    );
+    accept_counter.accounts[5].pubkey = maker_ta_a;
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            client::make_counter_offer(taker, &escrow_data, token_program, 40_000, 30_000),
+            accept_counter,
+        ],
+        &[
+            vec![
+                (taker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (mint_a, mint_account(&mollusk, &token_program)),
+                (mint_b, mint_account(&mollusk, &token_program)),
+                (
+                    maker_ta_a,
+                    token_account(&mollusk, &token_program, mint_a, maker, 0),
+                ),
+                (
+                    maker_ta_b,
+                    token_account(&mollusk, &token_program, mint_b, maker, 0),
+                ),
+                (
+                    taker_ta_b,
+                    token_account(&mollusk, &token_program, mint_b, taker, 1_000_000),
+                ),
+                (escrow, escrow_account),
+                (
+                    vault,
+                    token_account(&mollusk, &token_program, mint_a, escrow, 100_000),
+                ),
+                (counter, Account::default()),
+                (counter_vault, Account::default()),
+                (config, Account::default()),
+            ],
+            program_accounts(),
+        ]
+        .concat(),
+    );
+
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidTakerTokenAccount as u32
+    ));
+}
+
+#[test]
+fn update_zero_receive() {
+    let mollusk = mollusk();
+    let token_program = spl_token::ID;
+
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let maker_ta_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &mint_a,
+        &token_program,
+    );
+    let (escrow, escrow_bump) = client::find_escrow_address(&maker, 1337);
+    let (vault, vault_bump) = client::find_vault_address(&escrow);
+
+    let mut escrow_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow_account
+        .data_as_mut_slice()
+        .copy_from_slice(bytemuck::bytes_of(&Escrow {
+            discriminator: ESCROW_DISCRIMINATOR,
+            version: ESCROW_VERSION,
+            bump: escrow_bump,
+            vault_bump,
+            seed: 1337,
+            maker,
+            mint_a,
+            mint_b,
+            receive: 100_000,
+            remaining: 100_000,
+            ..Escrow::zeroed()
+        }));
+
+    // Keep the deposit but ask for nothing in return
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[
+            &[0x06],
+            &0u64.to_le_bytes()[..],
+            &0u64.to_le_bytes()[..],
+            &0u64.to_le_bytes()[..],
+            &0i64.to_le_bytes()[..],
+            Pubkey::default().as_ref(),
+        ]
+        .concat(),
+        vec![
+            AccountMeta::new(maker, true),
+            AccountMeta::new_readonly(mint_a, false),
+            AccountMeta::new(maker_ta_a, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+        ],
+    );
+
+    let result = mollusk.process_instruction(
+        &instruction,
+        &[
+            vec![
+                (maker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+                (mint_a, mint_account(&mollusk, &token_program)),
+                (
+                    maker_ta_a,
+                    token_account(&mollusk, &token_program, mint_a, maker, 0),
+                ),
+                (escrow, escrow_account),
+                (
+                    vault,
+                    token_account(&mollusk, &token_program, mint_a, escrow, 100_000),
+                ),
+            ],
+            program_accounts(),
+        ]
+        .concat(),
+    );
+
+    assert!(matches!(
+        result.program_result,
+        ProgramResult::Failure(ProgramError::Custom(code)) if code == EscrowError::InvalidReceive as u32
+    ));
 }
@@ -0,0 +1,279 @@
+// The optimized entrypoint run on the host against hand-serialized input. The
+// mollusk tests in the parent module cover it on-chain once the ELF is built
+// with `cargo build-sbf --features optimized`.
+use bytemuck::Zeroable;
+use core::mem::{self, MaybeUninit};
+
+use pinocchio::{account_info::AccountInfo as PinocchioAccountInfo, MAX_TX_ACCOUNTS, SUCCESS};
+use solana_program::entrypoint::{BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER};
+use solana_sdk::{
+    account::Account, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+};
+
+use crate::{
+    context::{Make, Take},
+    error::EscrowError,
+    optimized::{entrypoint::entrypoint, FastPath},
+    states::{Config, Escrow, OraclePricing, ESCROW_DISCRIMINATOR, ESCROW_VERSION, FEE_LEG_A},
+};
+
+// Lay out the input the way the runtime does, repeated keys as duplicates.
+// Backed by u64s so account data is aligned as on-chain.
+fn serialize(accounts: &[(Pubkey, bool, bool, &Account)], data: &[u8]) -> Vec<u64> {
+    let mut input = (accounts.len() as u64).to_le_bytes().to_vec();
+    for (i, (key, is_signer, is_writable, account)) in accounts.iter().enumerate() {
+        if let Some(index) = accounts[..i].iter().position(|(other, ..)| other == key) {
+            input.extend_from_slice(&[index as u8, 0, 0, 0, 0, 0, 0, 0]);
+            continue;
+        }
+        input.extend_from_slice(&[NON_DUP_MARKER, *is_signer as u8, *is_writable as u8, 0]);
+        input.extend_from_slice(&(account.data.len() as u32).to_le_bytes());
+        input.extend_from_slice(key.as_ref());
+        input.extend_from_slice(account.owner.as_ref());
+        input.extend_from_slice(&account.lamports.to_le_bytes());
+        input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&account.data);
+        input.resize(
+            (input.len() + MAX_PERMITTED_DATA_INCREASE).next_multiple_of(BPF_ALIGN_OF_U128),
+            0,
+        );
+        input.extend_from_slice(&account.rent_epoch.to_le_bytes());
+    }
+    input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    input.extend_from_slice(data);
+    input.extend_from_slice(crate::ID.as_ref());
+
+    let mut aligned = vec![0u64; input.len().div_ceil(8)];
+    bytemuck::cast_slice_mut::<u64, u8>(&mut aligned)[..input.len()].copy_from_slice(&input);
+    aligned
+}
+
+// Whether the fast path takes the instruction rather than the native processor
+fn takes_fast_path(accounts: &[(Pubkey, bool, bool, &Account)], data: &[u8]) -> bool {
+    let mut input = serialize(accounts, data);
+    const UNINIT: MaybeUninit<PinocchioAccountInfo> = MaybeUninit::uninit();
+    let mut account_infos = [UNINIT; MAX_TX_ACCOUNTS];
+    unsafe {
+        let (program_id, count, data) = pinocchio::entrypoint::deserialize::<MAX_TX_ACCOUNTS>(
+            input.as_mut_ptr() as *mut u8,
+            &mut account_infos,
+        );
+        let account_infos = core::slice::from_raw_parts(
+            account_infos.as_ptr() as *const PinocchioAccountInfo,
+            count,
+        );
+        FastPath::new(program_id, account_infos, data).is_some()
+    }
+}
+
+fn mint(token_program: &Pubkey) -> Account {
+    let mut account = Account::new(
+        Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        token_program,
+    );
+    Pack::pack(
+        spl_token::state::Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        },
+        &mut account.data,
+    )
+    .unwrap();
+    account
+}
+
+#[test]
+fn optimized_fast_path() {
+    let maker = Pubkey::new_from_array([0x01; 32]);
+    let mint_a = Pubkey::new_from_array([0x02; 32]);
+    let mint_b = Pubkey::new_from_array([0x03; 32]);
+    let native = spl_token::native_mint::ID;
+    let wallet = Account::new(1_000_000_000, 0, &Pubkey::default());
+    let empty = Account::default();
+    let classic = mint(&spl_token::ID);
+    let token_2022 = mint(&spl_token_2022::ID);
+
+    let make = |receive: u64, pricing: OraclePricing| {
+        [
+            &[0x00][..],
+            bytemuck::bytes_of(&Make {
+                seed: 1337,
+                amount: 100_000,
+                receive,
+                expiry: 0,
+                taker: Pubkey::default(),
+                pricing,
+            }),
+        ]
+        .concat()
+    };
+    // Token A from a classic mint, or `mint_a` itself, for token B under `token_program_b`
+    let make_accounts = |mint_a: Pubkey, token_program_b: Pubkey| {
+        vec![
+            (maker, true, true, &wallet),
+            (mint_a, false, false, &classic),
+            (
+                mint_b,
+                false,
+                false,
+                if token_program_b == spl_token::ID {
+                    &classic
+                } else {
+                    &token_2022
+                },
+            ),
+            (Pubkey::new_from_array([0x04; 32]), false, true, &empty),
+            (Pubkey::new_from_array([0x05; 32]), false, true, &empty),
+            (Pubkey::new_from_array([0x06; 32]), false, true, &empty),
+            (spl_token::ID, false, false, &empty),
+            (token_program_b, false, false, &empty),
+            (solana_sdk::system_program::ID, false, false, &empty),
+            (crate::ID, false, false, &empty),
+        ]
+    };
+
+    // A fixed-price offer between classic SPL Token mints takes the fast path
+    assert!(takes_fast_path(
+        &make_accounts(mint_a, spl_token::ID),
+        &make(40_000, OraclePricing::default()),
+    ));
+
+    // Native SOL, Token-2022, price feeds, an empty ask and other instructions
+    // are left to the native processor
+    for (accounts, data) in [
+        (
+            make_accounts(native, spl_token::ID),
+            make(40_000, OraclePricing::default()),
+        ),
+        (
+            make_accounts(mint_a, spl_token_2022::ID),
+            make(40_000, OraclePricing::default()),
+        ),
+        (
+            make_accounts(mint_a, spl_token::ID),
+            make(
+                0,
+                OraclePricing {
+                    oracle: Pubkey::new_from_array([0x07; 32]),
+                    ..OraclePricing::default()
+                },
+            ),
+        ),
+        (
+            make_accounts(mint_a, spl_token::ID),
+            make(0, OraclePricing::default()),
+        ),
+        (
+            make_accounts(mint_a, spl_token::ID),
+            [&[0x05][..], &25u16.to_le_bytes(), &[FEE_LEG_A]].concat(),
+        ),
+    ] {
+        assert!(!takes_fast_path(&accounts, &data));
+    }
+
+    // So are fills of a listed offer
+    let escrow_data = Escrow {
+        discriminator: ESCROW_DISCRIMINATOR,
+        version: ESCROW_VERSION,
+        seed: 1337,
+        maker,
+        mint_a,
+        mint_b,
+        receive: 40_000,
+        remaining: 100_000,
+        registry: Pubkey::new_from_array([0x08; 32]),
+        ..Escrow::zeroed()
+    };
+    let mut escrow = Account::new(
+        Rent::default().minimum_balance(mem::size_of::<Escrow>()),
+        mem::size_of::<Escrow>(),
+        &crate::ID,
+    );
+    escrow
+        .data
+        .copy_from_slice(bytemuck::bytes_of(&escrow_data));
+    let take_accounts = [
+        (Pubkey::new_from_array([0x09; 32]), true, true, &wallet),
+        (maker, false, true, &wallet),
+        (mint_a, false, false, &classic),
+        (mint_b, false, false, &classic),
+        (Pubkey::new_from_array([0x0a; 32]), false, true, &empty),
+        (Pubkey::new_from_array([0x0b; 32]), false, true, &empty),
+        (Pubkey::new_from_array([0x0c; 32]), false, true, &empty),
+        (Pubkey::new_from_array([0x0d; 32]), false, true, &escrow),
+        (Pubkey::new_from_array([0x0e; 32]), false, true, &empty),
+        (spl_token::ID, false, false, &empty),
+        (spl_token::ID, false, false, &empty),
+        (solana_sdk::system_program::ID, false, false, &empty),
+        (spl_associated_token_account::ID, false, false, &empty),
+        (crate::ID, false, false, &empty),
+        (crate::ID, false, false, &empty),
+        (escrow_data.registry, false, true, &empty),
+        (crate::ID, false, false, &empty),
+    ];
+    let take = [&[0x01][..], bytemuck::bytes_of(&Take::ANY)].concat();
+    assert!(!takes_fast_path(&take_accounts, &take));
+}
+
+#[test]
+fn optimized_native_fallback() {
+    let admin = Pubkey::new_from_array([0x08; 32]);
+    let treasury = Pubkey::new_from_array([0x0b; 32]);
+    let (config, bump) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+    let mut config_account = Account::new(
+        Rent::default().minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account
+        .data
+        .copy_from_slice(bytemuck::bytes_of(&Config {
+            admin,
+            treasury: Pubkey::new_from_array([0x09; 32]),
+            fee_bps: 25,
+            fee_leg: FEE_LEG_A,
+            bump,
+            _padding: [0; 4],
+        }));
+    let wallet = Account::new(1_000_000_000, 0, &Pubkey::default());
+    let empty = Account::default();
+    let update = [&[0x05][..], &50u16.to_le_bytes(), &[FEE_LEG_A]].concat();
+
+    // UpdateConfig goes through the native processor, which must see the admin
+    // passed again as the new admin as the same account
+    let mut input = serialize(
+        &[
+            (admin, true, true, &wallet),
+            (config, false, true, &config_account),
+            (treasury, false, false, &empty),
+            (admin, true, true, &wallet),
+        ],
+        &update,
+    );
+    let input = input.as_mut_ptr() as *mut u8;
+    assert_eq!(unsafe { entrypoint(input) }, SUCCESS);
+    let (_, accounts, _) = unsafe { solana_program::entrypoint::deserialize(input) };
+    assert_eq!(accounts[3].key, &admin);
+    let config_data = *bytemuck::from_bytes::<Config>(&accounts[1].data.borrow());
+    assert_eq!(config_data.admin, admin);
+    assert_eq!(config_data.treasury, treasury);
+    assert_eq!(config_data.fee_bps, 50);
+
+    // And rejects it with the native error
+    let stranger = Pubkey::new_from_array([0x0c; 32]);
+    let mut input = serialize(
+        &[
+            (stranger, true, true, &wallet),
+            (config, false, true, &config_account),
+            (treasury, false, false, &empty),
+            (stranger, true, true, &wallet),
+        ],
+        &update,
+    );
+    assert_eq!(
+        unsafe { entrypoint(input.as_mut_ptr() as *mut u8) },
+        u64::from(ProgramError::Custom(EscrowError::InvalidAdmin as u32))
+    );
+}
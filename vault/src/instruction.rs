@@ -1,20 +1,51 @@
+use shank::ShankInstruction;
 use solana_program::program_error::ProgramError;
 
+#[derive(ShankInstruction)]
 pub enum VaultInstruction {
+    #[account(0, writable, signer, name = "user", desc = "Owner of the vault")]
+    #[account(1, writable, name = "state", desc = "State PDA [\"state\", user]")]
+    #[account(2, name = "vault", desc = "Vault PDA [\"vault\", state]")]
+    #[account(3, name = "system_program", desc = "System program")]
     InitialiseVault,
-    Deposit,
-    Withdraw,
+
+    #[account(0, writable, signer, name = "user", desc = "Owner of the vault")]
+    #[account(1, name = "state", desc = "State PDA [\"state\", user]")]
+    #[account(2, writable, name = "vault", desc = "Vault PDA [\"vault\", state]")]
+    #[account(3, name = "system_program", desc = "System program")]
+    Deposit { amount: u64 },
+
+    #[account(0, writable, signer, name = "user", desc = "Owner of the vault")]
+    #[account(1, name = "state", desc = "State PDA [\"state\", user]")]
+    #[account(2, writable, name = "vault", desc = "Vault PDA [\"vault\", state]")]
+    #[account(3, name = "system_program", desc = "System program")]
+    Withdraw { amount: u64 },
+
+    #[account(0, writable, signer, name = "user", desc = "Owner of the vault")]
+    #[account(1, writable, name = "state", desc = "State PDA [\"state\", user]")]
+    #[account(2, writable, name = "vault", desc = "Vault PDA [\"vault\", state]")]
+    #[account(3, name = "system_program", desc = "System program")]
     Close,
 }
 
-impl TryFrom<&u8> for VaultInstruction {
+impl TryFrom<&[u8]> for VaultInstruction {
     type Error = ProgramError;
 
-    fn try_from(value: &u8) -> Result<Self, Self::Error> {
-        match value {
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let (discriminator, data) = value
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        let amount = || {
+            data.get(..8)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(ProgramError::InvalidInstructionData)
+        };
+
+        match discriminator {
             0 => Ok(Self::InitialiseVault),
-            1 => Ok(Self::Deposit),
-            2 => Ok(Self::Withdraw),
+            1 => Ok(Self::Deposit { amount: amount()? }),
+            2 => Ok(Self::Withdraw { amount: amount()? }),
             3 => Ok(Self::Close),
             _ => Err(ProgramError::InvalidInstructionData),
         }
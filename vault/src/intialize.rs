@@ -1,7 +1,7 @@
 use crate::state::VaultState;
 use borsh::BorshSerialize;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::AccountInfo,
     entrypoint::ProgramResult,
     // example_mocks::solana_sdk::system_instruction,
     program::invoke,
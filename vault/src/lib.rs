@@ -8,8 +8,7 @@ pub mod state;
 pub mod withdraw;
 
 use solana_program::{
-    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, program_error::ProgramError,
-    pubkey, pubkey::Pubkey,
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey,
 };
 
 use crate::instruction::VaultInstruction;
@@ -21,30 +20,16 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
-    let (discriminator, data) = data
-        .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
-
-    let instruction = VaultInstruction::try_from(discriminator)?;
+    let instruction = VaultInstruction::try_from(data)?;
 
     match instruction {
         VaultInstruction::InitialiseVault => {
             intialize::process(program_id, accounts)?;
         }
-        VaultInstruction::Deposit => {
-            let amount = data
-                .get(..8)
-                .and_then(|bytes| bytes.try_into().ok())
-                .map(u64::from_le_bytes)
-                .ok_or(ProgramError::InvalidInstructionData)?;
+        VaultInstruction::Deposit { amount } => {
             deposit::process(program_id, accounts, amount)?;
         }
-        VaultInstruction::Withdraw => {
-            let amount = data
-                .get(..8)
-                .and_then(|bytes| bytes.try_into().ok())
-                .map(u64::from_le_bytes)
-                .ok_or(ProgramError::InvalidInstructionData)?;
+        VaultInstruction::Withdraw { amount } => {
             withdraw::process(program_id, accounts, amount)?;
         }
         VaultInstruction::Close => {
@@ -1,6 +1,7 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use shank::ShankAccount;
 
-#[derive(BorshDeserialize, BorshSerialize, Debug, Default)]
+#[derive(BorshDeserialize, BorshSerialize, Debug, Default, ShankAccount)]
 pub struct VaultState {
     pub state_bump: u8,
     pub vault_bump: u8,